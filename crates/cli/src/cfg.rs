@@ -3,10 +3,11 @@ use clap::{Args, Parser, Subcommand, ValueEnum};
 use config::{builder::DefaultState, ConfigBuilder, File};
 use dirs::home_dir;
 use dotenvy::dotenv;
+use keystore_rs::{KeyChain, KeyStore, KeyStoreType};
 use log::{error, warn};
 use prism_errors::{DataAvailabilityError, GeneralError};
-use prism_keys::VerifyingKey;
-use prism_prover::webserver::WebServerConfig;
+use prism_keys::{CryptoAlgorithm, KeyFileSigner, Signer, SigningKey, VerifyingKey};
+use prism_prover::{remote_signer::RemoteSigner, webserver::WebServerConfig};
 use prism_serde::base64::FromBase64;
 use prism_storage::{
     database::StorageBackend,
@@ -27,6 +28,9 @@ use prism_da::{
 
 use crate::network::{Network, NetworkConfig};
 
+/// Environment variable holding the password of the signing key file
+const SIGNING_KEY_PASSWORD_ENV: &str = "PRISM_SIGNING_KEY_PASSWORD";
+
 #[derive(Clone, Debug, Subcommand, Deserialize)]
 pub enum Commands {
     LightClient(CommandArgs),
@@ -58,6 +62,9 @@ pub struct CommandArgs {
 
     #[command(flatten)]
     webserver: WebserverArgs,
+
+    #[command(flatten)]
+    signer: SignerArgs,
 }
 
 #[derive(Parser, Clone, Debug, Deserialize)]
@@ -102,6 +109,18 @@ struct WebserverArgs {
     port: Option<u16>,
 }
 
+#[derive(Args, Deserialize, Clone, Debug)]
+#[group(required = false, multiple = false)]
+struct SignerArgs {
+    /// Encrypted PKCS#8 PEM file holding the prover's signing key. The password is read from the `PRISM_SIGNING_KEY_PASSWORD` environment variable.
+    #[arg(long)]
+    signing_key_file: Option<String>,
+
+    /// Remote signer holding the prover's signing key, either `http://<host>:<port>` or `unix:<path>`. Default: the key in the system keychain
+    #[arg(long)]
+    remote_signer: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -265,6 +284,8 @@ fn apply_command_line_args(config: Config, args: CommandArgs) -> Config {
                 .and_then(|x| VerifyingKey::from_base64(x).ok())
                 .or(network_config.clone().verifying_key),
             celestia_config,
+            // Only custom networks take their parameters from the config file
+            params: config.network.params.clone(),
         },
         da_layer: config.da_layer,
     }
@@ -291,6 +312,31 @@ pub fn initialize_db(cfg: &Config) -> Result<Arc<Box<dyn Database>>> {
     }
 }
 
+pub fn initialize_signer(args: &CommandArgs) -> Result<Arc<dyn Signer>> {
+    if let Some(endpoint) = &args.signer.remote_signer {
+        let signer =
+            RemoteSigner::connect(endpoint).context("Failed to connect to remote signer")?;
+        return Ok(Arc::new(signer));
+    }
+
+    if let Some(path) = &args.signer.signing_key_file {
+        let password = std::env::var(SIGNING_KEY_PASSWORD_ENV)
+            .with_context(|| format!("{} is not set", SIGNING_KEY_PASSWORD_ENV))?;
+        let signer = KeyFileSigner::open(path, password.as_bytes())?;
+        return Ok(Arc::new(signer));
+    }
+
+    let signing_key_chain = KeyStoreType::KeyChain(KeyChain)
+        .get_signing_key()
+        .map_err(|e| GeneralError::InitializationError(e.to_string()))?;
+    let signing_key = SigningKey::from_algorithm_and_bytes(
+        CryptoAlgorithm::Ed25519,
+        signing_key_chain.as_bytes(),
+    )
+    .context("Invalid signing key")?;
+    Ok(Arc::new(signing_key))
+}
+
 pub async fn initialize_da_layer(
     config: &Config,
 ) -> Result<Arc<dyn DataAvailabilityLayer + 'static>> {
@@ -2,10 +2,9 @@ mod cfg;
 mod network;
 mod node_types;
 
-use cfg::{initialize_da_layer, initialize_db, load_config, Cli, Commands};
+use cfg::{initialize_da_layer, initialize_db, initialize_signer, load_config, Cli, Commands};
 use clap::Parser;
-use keystore_rs::{KeyChain, KeyStore, KeyStoreType};
-use prism_keys::{CryptoAlgorithm, SigningKey};
+use prism_keys::Signer;
 use prism_serde::base64::ToBase64;
 use sp1_sdk::{HashableKey, Prover as _, ProverClient};
 use std::io::{Error, ErrorKind};
@@ -49,14 +48,16 @@ async fn main() -> std::io::Result<()> {
                 start_height,
                 verifying_key,
                 vk.bytes32(),
+                config.network.protocol_params(),
             ))
         }
         Commands::Prover(_) => {
             let db =
                 initialize_db(&config).map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
 
-            let signing_key = get_signing_key()?;
-            let verifying_key = signing_key.verifying_key();
+            let signer = initialize_signer(&args)
+                .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+            let verifying_key = signer.verifying_key();
 
             info!(
                 "prover's verifying key: {}",
@@ -67,9 +68,10 @@ async fn main() -> std::io::Result<()> {
                 prover: true,
                 batcher: true,
                 webserver: config.webserver.unwrap_or_default(),
-                signing_key,
+                signer,
                 verifying_key,
                 start_height,
+                params: config.network.protocol_params(),
             };
 
             Arc::new(Prover::new(db, da, &prover_cfg).map_err(|e| {
@@ -81,7 +83,8 @@ async fn main() -> std::io::Result<()> {
             let db =
                 initialize_db(&config).map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
 
-            let signing_key = get_signing_key()?;
+            let signer = initialize_signer(&args)
+                .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
 
             let verifying_key = config
                 .network
@@ -92,9 +95,10 @@ async fn main() -> std::io::Result<()> {
                 prover: false,
                 batcher: true,
                 webserver: config.webserver.unwrap_or_default(),
-                signing_key,
+                signer,
                 verifying_key,
                 start_height,
+                params: config.network.protocol_params(),
             };
 
             Arc::new(Prover::new(db, da, &prover_cfg).map_err(|e| {
@@ -106,17 +110,3 @@ async fn main() -> std::io::Result<()> {
 
     node.start().await.map_err(|e| Error::new(ErrorKind::Other, e.to_string()))
 }
-
-fn get_signing_key() -> std::io::Result<SigningKey> {
-    let signing_key_chain = KeyStoreType::KeyChain(KeyChain)
-        .get_signing_key()
-        .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
-
-    SigningKey::from_algorithm_and_bytes(CryptoAlgorithm::Ed25519, signing_key_chain.as_bytes())
-        .map_err(|e| {
-            Error::new(
-                ErrorKind::InvalidInput,
-                format!("Invalid signing key: {}", e),
-            )
-        })
-}
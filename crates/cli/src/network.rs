@@ -1,6 +1,7 @@
 use std::str::FromStr;
 
 use lumina_node::network::Network as CelestiaNetwork;
+use prism_common::params::ProtocolParams;
 use prism_da::celestia::CelestiaConfig;
 use prism_keys::VerifyingKey;
 use prism_serde::{self, base64::FromBase64};
@@ -19,6 +20,21 @@ pub struct NetworkConfig {
     /// The verifying key of the prover
     pub verifying_key: Option<VerifyingKey>,
     pub celestia_config: Option<CelestiaConfig>,
+    /// Overrides the protocol parameters of custom networks. Known networks
+    /// always use their own, see [`NetworkConfig::protocol_params`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<ProtocolParams>,
+}
+
+impl NetworkConfig {
+    /// Returns the protocol parameters all transactions of the network are
+    /// validated against.
+    pub fn protocol_params(&self) -> ProtocolParams {
+        match (&self.network, &self.params) {
+            (Network::Custom(_), Some(params)) => params.clone(),
+            _ => self.network.params(),
+        }
+    }
 }
 
 impl Default for NetworkConfig {
@@ -28,6 +44,7 @@ impl Default for NetworkConfig {
             celestia_network: CelestiaNetwork::Private,
             verifying_key: None,
             celestia_config: None,
+            params: None,
         }
     }
 }
@@ -61,6 +78,7 @@ impl Network {
                         "000000000000000000000000000000000000707269736d5350456f30".to_string(),
                     ..CelestiaConfig::default()
                 }),
+                params: None,
             },
             Network::Custom(id) => NetworkConfig {
                 network: Network::Custom(id.clone()),
@@ -68,4 +86,12 @@ impl Network {
             },
         }
     }
+
+    /// Returns the default protocol parameters of the network.
+    pub fn params(&self) -> ProtocolParams {
+        match self {
+            Network::Specter => ProtocolParams::for_chain("specter"),
+            Network::Custom(id) => ProtocolParams::for_chain(id),
+        }
+    }
 }
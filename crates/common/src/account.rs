@@ -1,11 +1,13 @@
-use anyhow::{anyhow, Result};
-use prism_keys::{Signature, SigningKey, VerifyingKey};
+use anyhow::{anyhow, bail, Result};
+use prism_keys::{EncryptionKey, SafetyNumber, Signature, Signer, VerifyingKey};
 use prism_serde::raw_or_b64;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::{
-    operation::{Operation, ServiceChallenge},
+    digest::Digest,
+    operation::{DataSelector, Operation, ServiceChallenge},
+    params::{ProtocolParams, ValidationContext},
     transaction::Transaction,
 };
 
@@ -23,6 +25,164 @@ pub struct SignedData {
     pub data: Vec<u8>,
 }
 
+impl SignedData {
+    /// Returns the digest of the signed data, which can be used to select the
+    /// entry in [`Operation::RemoveData`].
+    pub fn digest(&self) -> Digest {
+        Digest::hash(&self.data)
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+/// The capabilities of a key stored in an account.
+pub enum KeyRole {
+    /// The key may authorize every operation on the account.
+    #[default]
+    Admin,
+    /// The key may only add, replace and remove the account's data.
+    DataOnly,
+    /// The key cannot authorize any operation. It only belongs to the account
+    /// to sign data, e.g. for [`Operation::AddData`].
+    SigningOnly,
+}
+
+impl KeyRole {
+    /// Returns true if a key with this role may authorize the operation.
+    pub fn permits(&self, operation: &Operation) -> bool {
+        match self {
+            KeyRole::Admin => true,
+            KeyRole::DataOnly => matches!(
+                operation,
+                Operation::AddData { .. }
+                    | Operation::SetData { .. }
+                    | Operation::RemoveData { .. }
+            ),
+            KeyRole::SigningOnly => false,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+/// A key that is allowed to sign transactions for an account, optionally
+/// only up to a given epoch.
+pub struct AccountKey {
+    /// The verifying key
+    pub key: VerifyingKey,
+    /// The last epoch in which the key may be used. The key does not expire if
+    /// this is `None`.
+    #[schema(example = 42)]
+    pub valid_until: Option<u64>,
+    /// The operations the key may authorize
+    #[serde(default)]
+    pub role: KeyRole,
+}
+
+impl AccountKey {
+    pub fn new(key: VerifyingKey, valid_until: Option<u64>) -> Self {
+        Self::with_role(key, valid_until, KeyRole::Admin)
+    }
+
+    pub fn with_role(key: VerifyingKey, valid_until: Option<u64>, role: KeyRole) -> Self {
+        AccountKey {
+            key,
+            valid_until,
+            role,
+        }
+    }
+
+    /// Returns true if the key can no longer be used at the given epoch.
+    pub fn is_expired(&self, epoch: u64) -> bool {
+        self.valid_until.is_some_and(|valid_until| epoch > valid_until)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+/// The authorization policy of an account.
+pub struct AccountPolicy {
+    /// Number of distinct active keys that need to sign sensitive operations
+    /// (see [`Operation::is_sensitive`]). Operations that would leave the
+    /// account with fewer admin keys than this are rejected.
+    #[schema(example = 2)]
+    pub threshold: u32,
+}
+
+impl AccountPolicy {
+    /// Creates a policy requiring `threshold` signatures for sensitive operations.
+    pub fn threshold(threshold: u32) -> Self {
+        AccountPolicy { threshold }
+    }
+
+    pub fn validate_basic(&self) -> Result<()> {
+        if self.threshold == 0 {
+            bail!("Policy threshold must be at least 1");
+        }
+        Ok(())
+    }
+
+    /// Returns the number of signatures a sensitive operation needs when
+    /// `permitted_keys` keys may authorize it. Fails if there are fewer such
+    /// keys than the threshold, e.g. because keys expired in the meantime.
+    pub fn required_signatures(&self, permitted_keys: usize) -> Result<usize> {
+        if permitted_keys < self.threshold as usize {
+            bail!(
+                "Policy requires {} signatures, but only {} keys may authorize the operation",
+                self.threshold,
+                permitted_keys
+            );
+        }
+        Ok(self.threshold as usize)
+    }
+}
+
+impl Default for AccountPolicy {
+    fn default() -> Self {
+        AccountPolicy::threshold(1)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+/// Keys that can regain control over an account after all of its regular keys
+/// have been lost.
+pub struct RecoveryConfig {
+    /// Keys allowed to initiate and complete a recovery
+    pub keys: Vec<VerifyingKey>,
+    /// Number of epochs between initiating and completing a recovery, during
+    /// which the account's keys can cancel it.
+    #[schema(example = 100)]
+    pub delay: u64,
+}
+
+impl RecoveryConfig {
+    pub fn new(keys: Vec<VerifyingKey>, delay: u64) -> Self {
+        RecoveryConfig { keys, delay }
+    }
+
+    pub fn validate_basic(&self, params: &ProtocolParams) -> Result<()> {
+        if self.keys.is_empty() {
+            bail!("Recovery config must contain at least one key");
+        }
+        if self.keys.len() > params.max_keys_per_account as usize {
+            bail!(
+                "Recovery config has {} keys, maximum is {}",
+                self.keys.len(),
+                params.max_keys_per_account
+            );
+        }
+        self.keys.iter().try_for_each(|key| params.validate_key(key))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+/// A recovery that has been initiated, but not completed or cancelled yet.
+pub struct PendingRecovery {
+    /// The key that replaces all keys of the account once recovery completes
+    pub key: VerifyingKey,
+    /// The first epoch in which the recovery can be completed
+    #[schema(example = 142)]
+    pub completable_at: u64,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default, ToSchema)]
 /// Represents an account or service on prism, making up the values of our state
 /// tree.
@@ -34,8 +194,14 @@ pub struct Account {
     nonce: u64,
 
     /// The current set of valid keys for the account. Any of these keys can be
-    /// used to sign transactions.
-    valid_keys: Vec<VerifyingKey>,
+    /// used to sign transactions until it expires, while sensitive operations
+    /// need as many signatures as the policy requires.
+    valid_keys: Vec<AccountKey>,
+
+    /// Keys that data can be encrypted for, to be read by the account's
+    /// owner. They are published alongside the signing keys, but can never
+    /// authorize transactions.
+    encryption_keys: Vec<EncryptionKey>,
 
     /// Arbitrary signed data associated with the account, used for bookkeeping
     /// externally signed data from keys that don't live on Prism.
@@ -43,6 +209,23 @@ pub struct Account {
 
     /// The service challenge for the account, if it is a service.
     service_challenge: Option<ServiceChallenge>,
+
+    /// The policy determining how many keys need to sign sensitive operations.
+    policy: AccountPolicy,
+
+    /// The keys that can recover the account, if recovery is enabled.
+    recovery: Option<RecoveryConfig>,
+
+    /// The recovery currently in progress, if any.
+    pending_recovery: Option<PendingRecovery>,
+
+    /// The service that may add and revoke keys on behalf of the account, if
+    /// the account delegated to it. Completing a recovery revokes it.
+    delegate: Option<String>,
+
+    /// Whether the account has been deactivated. Deactivated accounts have no
+    /// keys or data and cannot be modified anymore.
+    deactivated: bool,
 }
 
 impl Account {
@@ -54,10 +237,50 @@ impl Account {
         self.nonce
     }
 
-    pub fn valid_keys(&self) -> &[VerifyingKey] {
+    pub fn valid_keys(&self) -> &[AccountKey] {
         &self.valid_keys
     }
 
+    pub fn encryption_keys(&self) -> &[EncryptionKey] {
+        &self.encryption_keys
+    }
+
+    /// Returns the entry of the given key in the account, regardless of
+    /// whether it is expired.
+    pub fn get_key(&self, key: &VerifyingKey) -> Option<&AccountKey> {
+        self.valid_keys.iter().find(|k| &k.key == key)
+    }
+
+    /// Returns true if the account contains the given key, regardless of
+    /// whether it is expired.
+    pub fn contains_key(&self, key: &VerifyingKey) -> bool {
+        self.valid_keys.iter().any(|k| &k.key == key)
+    }
+
+    /// Returns true if the given key can be used for the account at the given epoch.
+    pub fn is_active_key(&self, key: &VerifyingKey, epoch: u64) -> bool {
+        self.valid_keys.iter().any(|k| &k.key == key && !k.is_expired(epoch))
+    }
+
+    /// Returns the keys of the account that have expired at the given epoch.
+    pub fn expired_keys(&self, epoch: u64) -> Vec<&VerifyingKey> {
+        self.valid_keys.iter().filter(|k| k.is_expired(epoch)).map(|k| &k.key).collect()
+    }
+
+    /// Returns the keys that can be used for the account at the given epoch.
+    pub fn active_keys(&self, epoch: u64) -> Vec<&VerifyingKey> {
+        self.valid_keys.iter().filter(|k| !k.is_expired(epoch)).map(|k| &k.key).collect()
+    }
+
+    /// Returns the safety number of this and another account, computed from
+    /// the keys active at the given epoch. Both account holders compare it
+    /// out of band to detect keys they did not add. See [`SafetyNumber`].
+    pub fn safety_number(&self, other: &Account, epoch: u64) -> Result<SafetyNumber> {
+        let keys: Vec<VerifyingKey> = self.active_keys(epoch).into_iter().cloned().collect();
+        let other_keys: Vec<VerifyingKey> = other.active_keys(epoch).into_iter().cloned().collect();
+        SafetyNumber::new(&self.id, &keys, &other.id, &other_keys)
+    }
+
     pub fn signed_data(&self) -> &[SignedData] {
         &self.signed_data
     }
@@ -66,41 +289,157 @@ impl Account {
         self.service_challenge.as_ref()
     }
 
-    /// Creates a [`Transaction`] that can be used to update or create the
-    /// account. The transaction produced could be invalid, and will be
-    /// validated before being processed.
-    pub fn prepare_transaction(
+    pub fn policy(&self) -> &AccountPolicy {
+        &self.policy
+    }
+
+    pub fn recovery(&self) -> Option<&RecoveryConfig> {
+        self.recovery.as_ref()
+    }
+
+    pub fn pending_recovery(&self) -> Option<&PendingRecovery> {
+        self.pending_recovery.as_ref()
+    }
+
+    pub fn delegate(&self) -> Option<&str> {
+        self.delegate.as_deref()
+    }
+
+    pub fn is_deactivated(&self) -> bool {
+        self.deactivated
+    }
+
+    /// Returns the number of keys that can be used for the account at the given epoch.
+    pub fn active_key_count(&self, epoch: u64) -> usize {
+        self.valid_keys.iter().filter(|k| !k.is_expired(epoch)).count()
+    }
+
+    /// Returns the number of keys that can authorize the operation at the
+    /// given epoch. The account's policy thresholds are applied to these.
+    pub fn permitted_key_count(&self, operation: &Operation, epoch: u64) -> usize {
+        self.valid_keys.iter().filter(|k| !k.is_expired(epoch) && k.role.permits(operation)).count()
+    }
+
+    /// Creates a [`Transaction`] for the given chain that can be used to
+    /// update or create the account. The transaction produced could be
+    /// invalid, and will be validated before being processed.
+    pub fn prepare_transaction<S: Signer + ?Sized>(
         &self,
         account_id: String,
         operation: Operation,
-        sk: &SigningKey,
+        signer: &S,
+        chain_id: &str,
     ) -> Result<Transaction> {
-        let vk = sk.verifying_key();
+        self.prepare_transaction_with_operations(account_id, vec![operation], signer, chain_id)
+    }
+
+    /// Creates a [`Transaction`] applying multiple operations atomically. See
+    /// [`Account::prepare_transaction`].
+    pub fn prepare_transaction_with_operations<S: Signer + ?Sized>(
+        &self,
+        account_id: String,
+        operations: Vec<Operation>,
+        signer: &S,
+        chain_id: &str,
+    ) -> Result<Transaction> {
+        let vk = signer.verifying_key();
 
         let mut tx = Transaction {
             id: account_id,
             nonce: self.nonce,
-            operation,
+            operations,
             signature: Signature::Placeholder,
             vk,
+            cosignatures: Vec::new(),
+            delegated: false,
+            valid_until: None,
         };
 
-        tx.sign(sk)?;
+        tx.sign(signer, chain_id)?;
 
         Ok(tx)
     }
 
-    /// Validates and processes an incoming [`Transaction`], updating the account state.
-    pub fn process_transaction(&mut self, tx: &Transaction) -> Result<()> {
-        self.validate_transaction(tx)?;
-        self.process_operation(&tx.operation)?;
-        self.nonce += 1;
+    /// Validates and processes an incoming [`Transaction`] in the given
+    /// context, updating the account state. The operations of the transaction
+    /// are applied in order, and the account is only updated if all of them
+    /// succeed.
+    pub fn process_transaction(&mut self, tx: &Transaction, ctx: &ValidationContext) -> Result<()> {
+        if tx.delegated {
+            return Err(anyhow!(
+                "Delegated transaction requires the delegate service"
+            ));
+        }
+        self.apply_transaction(tx, None, ctx)
+    }
+
+    /// Validates and processes a [`Transaction`] signed by the keys of the
+    /// service the account has delegated to. See [`Account::process_transaction`].
+    pub fn process_delegated_transaction(
+        &mut self,
+        tx: &Transaction,
+        service: &Account,
+        ctx: &ValidationContext,
+    ) -> Result<()> {
+        if !tx.delegated {
+            return Err(anyhow!("Transaction is not delegated"));
+        }
+        if self.delegate.as_deref() != Some(service.id()) {
+            return Err(anyhow!(
+                "Account has not delegated to service {}",
+                service.id()
+            ));
+        }
+        self.apply_transaction(tx, Some(service), ctx)
+    }
+
+    fn apply_transaction(
+        &mut self,
+        tx: &Transaction,
+        service: Option<&Account>,
+        ctx: &ValidationContext,
+    ) -> Result<()> {
+        self.validate_transaction(tx, ctx)?;
+
+        let mut account = self.clone();
+        for operation in &tx.operations {
+            match service {
+                Some(service) => {
+                    account.authorize_delegated_operation(tx, operation, service, ctx.epoch)?
+                }
+                None => account.authorize_operation(tx, operation, ctx.epoch)?,
+            }
+            account.process_operation(tx, operation, ctx)?;
+        }
+        if !account.deactivated
+            && tx.operations.iter().any(|op| op.is_sensitive() || op.is_creation())
+        {
+            account.validate_policy(ctx.epoch)?;
+        }
+        account.nonce += 1;
+
+        *self = account;
         Ok(())
     }
 
-    /// Validates a transaction against the current account state. Please note
-    /// that the operation must be validated separately.
-    fn validate_transaction(&self, tx: &Transaction) -> Result<()> {
+    /// Validates a transaction in the given context against the current
+    /// account state. Please note that each operation must be authorized and
+    /// validated separately.
+    fn validate_transaction(&self, tx: &Transaction, ctx: &ValidationContext) -> Result<()> {
+        if self.deactivated {
+            return Err(anyhow!("Account is deactivated"));
+        }
+
+        if let Some(valid_until) = tx.valid_until {
+            if ctx.epoch > valid_until {
+                return Err(anyhow!(
+                    "Transaction expired in epoch {}, current epoch is {}",
+                    valid_until,
+                    ctx.epoch
+                ));
+            }
+        }
+
         if tx.nonce != self.nonce {
             return Err(anyhow!(
                 "Nonce does not match. {} != {}",
@@ -109,37 +448,185 @@ impl Account {
             ));
         }
 
-        match tx.operation {
-            Operation::CreateAccount { .. } | Operation::RegisterService { .. } => {}
-            _ => {
+        if tx.operations.is_empty() {
+            return Err(anyhow!("Transaction contains no operations"));
+        }
+        if tx.operations.iter().skip(1).any(Operation::is_creation) {
+            return Err(anyhow!("Account creation must be the first operation"));
+        }
+        if tx.operations.len() > 1 && tx.operations.iter().any(Operation::is_recovery) {
+            return Err(anyhow!("Recovery operations must be the only operation"));
+        }
+
+        for (i, signer) in tx.signers().enumerate() {
+            if tx.signers().take(i).any(|other| other == signer) {
+                return Err(anyhow!("Duplicate signer"));
+            }
+        }
+
+        let msg = tx.get_signature_payload(&ctx.params.chain_id)?;
+        ctx.verify_signature(&tx.vk, &msg, &tx.signature)?;
+        for cosignature in &tx.cosignatures {
+            ctx.verify_signature(&cosignature.verifying_key, &msg, &cosignature.signature)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the signers of the transaction are allowed to apply the
+    /// operation to the account in its current state.
+    fn authorize_operation(
+        &self,
+        tx: &Transaction,
+        operation: &Operation,
+        epoch: u64,
+    ) -> Result<()> {
+        match operation {
+            Operation::CreateAccount { key, .. } | Operation::RegisterService { key, .. } => {
+                // Binds all fields of the creation to the initial key, as the
+                // credentials of the service only cover id and key
+                if tx.vk != *key {
+                    return Err(anyhow!(
+                        "Account creation must be signed by its initial key"
+                    ));
+                }
+                if !tx.cosignatures.is_empty() {
+                    return Err(anyhow!("Account creation cannot be cosigned"));
+                }
+                return Ok(());
+            }
+            Operation::InitiateRecovery { .. } | Operation::CompleteRecovery => {
                 if tx.id != self.id {
                     return Err(anyhow!("Transaction ID does not match account ID"));
                 }
-                if !self.valid_keys.contains(&tx.vk) {
-                    return Err(anyhow!("Invalid key"));
+                if !tx.cosignatures.is_empty() {
+                    return Err(anyhow!("Recovery operations cannot be cosigned"));
+                }
+                let Some(recovery) = &self.recovery else {
+                    return Err(anyhow!("Account has no recovery keys"));
+                };
+                if !recovery.keys.contains(&tx.vk) {
+                    return Err(anyhow!("Invalid recovery key"));
                 }
+                return Ok(());
             }
+            _ => {}
         }
 
-        let msg = tx.get_signature_payload()?;
-        tx.vk.verify_signature(&msg, &tx.signature)?;
+        if tx.id != self.id {
+            return Err(anyhow!("Transaction ID does not match account ID"));
+        }
+        for signer in tx.signers() {
+            let Some(account_key) = self.get_key(signer) else {
+                return Err(anyhow!("Invalid key"));
+            };
+            if account_key.is_expired(epoch) {
+                return Err(anyhow!("Key expired"));
+            }
+            if !account_key.role.permits(operation) {
+                return Err(anyhow!(
+                    "Key with role {:?} cannot authorize this operation",
+                    account_key.role
+                ));
+            }
+        }
+
+        if operation.is_sensitive() {
+            let required =
+                self.policy.required_signatures(self.permitted_key_count(operation, epoch))?;
+            let provided = 1 + tx.cosignatures.len();
+            if provided < required {
+                return Err(anyhow!(
+                    "Operation requires {} signatures, but only {} were provided",
+                    required,
+                    provided
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the signers of a delegated transaction are allowed to apply
+    /// the operation on behalf of the account. The keys and policy of the
+    /// service take the place of the account's own.
+    fn authorize_delegated_operation(
+        &self,
+        tx: &Transaction,
+        operation: &Operation,
+        service: &Account,
+        epoch: u64,
+    ) -> Result<()> {
+        if tx.id != self.id {
+            return Err(anyhow!("Transaction ID does not match account ID"));
+        }
+        if !operation.is_delegable() {
+            return Err(anyhow!("Operation cannot be delegated to the service"));
+        }
+        for signer in tx.signers() {
+            let Some(service_key) = service.get_key(signer) else {
+                return Err(anyhow!("Invalid service key"));
+            };
+            if service_key.is_expired(epoch) {
+                return Err(anyhow!("Invalid service key"));
+            }
+            if !service_key.role.permits(operation) {
+                return Err(anyhow!(
+                    "Service key with role {:?} cannot authorize this operation",
+                    service_key.role
+                ));
+            }
+        }
+
+        let required =
+            service.policy.required_signatures(service.permitted_key_count(operation, epoch))?;
+        let provided = 1 + tx.cosignatures.len();
+        if provided < required {
+            return Err(anyhow!(
+                "Operation requires {} service signatures, but only {} were provided",
+                required,
+                provided
+            ));
+        }
 
         Ok(())
     }
 
     /// Validates an operation against the current account state.
-    fn validate_operation(&self, operation: &Operation) -> Result<()> {
+    fn validate_operation(
+        &self,
+        tx: &Transaction,
+        operation: &Operation,
+        ctx: &ValidationContext,
+    ) -> Result<()> {
+        operation.validate_basic(&ctx.params)?;
+
         match operation {
-            Operation::AddKey { key } => {
-                if self.valid_keys.contains(key) {
+            Operation::AddKey {
+                key, valid_until, ..
+            } => {
+                if self.contains_key(key) {
                     return Err(anyhow!("Key already exists"));
                 }
+                self.validate_key_count(&ctx.params)?;
+                Self::validate_key_expiry(*valid_until, ctx.epoch)?;
             }
             Operation::RevokeKey { key } => {
-                if !self.valid_keys.contains(key) {
+                if !self.contains_key(key) {
                     return Err(anyhow!("Key does not exist"));
                 }
             }
+            Operation::AddEncryptionKey { key } => {
+                if self.encryption_keys.contains(key) {
+                    return Err(anyhow!("Encryption key already exists"));
+                }
+                self.validate_key_count(&ctx.params)?;
+            }
+            Operation::RevokeEncryptionKey { key } => {
+                if !self.encryption_keys.contains(key) {
+                    return Err(anyhow!("Encryption key does not exist"));
+                }
+            }
             Operation::AddData {
                 data,
                 data_signature,
@@ -148,34 +635,149 @@ impl Account {
                 data,
                 data_signature,
             } => {
+                if matches!(operation, Operation::AddData { .. })
+                    && self.signed_data.len() >= ctx.params.max_signed_data_entries as usize
+                {
+                    return Err(anyhow!(
+                        "Account already has the maximum of {} data entries",
+                        ctx.params.max_signed_data_entries
+                    ));
+                }
+
                 // we only need to do a single signature verification if the
-                // user signs transaction and data with their own key
-                if !self.valid_keys().contains(&data_signature.verifying_key) {
-                    data_signature
-                        .verifying_key
-                        .verify_signature(data, &data_signature.signature)?;
+                // data is signed by a signer of the transaction, whose
+                // signature already covers the data
+                if !tx.signers().any(|signer| signer == &data_signature.verifying_key) {
+                    ctx.verify_signature(
+                        &data_signature.verifying_key,
+                        data,
+                        &data_signature.signature,
+                    )?;
+                }
+            }
+            Operation::RemoveData { selector } => match selector {
+                DataSelector::Index(index) => {
+                    if *index >= self.signed_data.len() as u64 {
+                        return Err(anyhow!("Data index {} out of bounds", index));
+                    }
+                }
+                DataSelector::Digest(digest) => {
+                    if !self.signed_data.iter().any(|entry| &entry.digest() == digest) {
+                        return Err(anyhow!("No data found for digest {:?}", digest));
+                    }
+                }
+            },
+            Operation::CreateAccount { valid_until, .. } => {
+                if !self.is_empty() {
+                    return Err(anyhow!("Account already exists"));
                 }
+                Self::validate_key_expiry(*valid_until, ctx.epoch)?;
             }
-            Operation::CreateAccount { .. } | Operation::RegisterService { .. } => {
+            Operation::RegisterService { .. } => {
                 if !self.is_empty() {
                     return Err(anyhow!("Account already exists"));
                 }
             }
+            Operation::UpdateServiceChallenge { .. } => {
+                if self.service_challenge.is_none() {
+                    return Err(anyhow!("Account is not a service"));
+                }
+            }
+            Operation::SetPolicy { .. } | Operation::SetRecovery { .. } => {}
+            Operation::InitiateRecovery { .. } => {
+                if self.pending_recovery.is_some() {
+                    return Err(anyhow!("Recovery already in progress"));
+                }
+            }
+            Operation::CancelRecovery => {
+                if self.pending_recovery.is_none() {
+                    return Err(anyhow!("No recovery in progress"));
+                }
+            }
+            Operation::RevokeDelegation => {
+                if self.delegate.is_none() {
+                    return Err(anyhow!("Account has not delegated to its service"));
+                }
+            }
+            Operation::DeactivateAccount => {}
+            Operation::CompleteRecovery => {
+                let Some(pending_recovery) = &self.pending_recovery else {
+                    return Err(anyhow!("No recovery in progress"));
+                };
+                if ctx.epoch < pending_recovery.completable_at {
+                    return Err(anyhow!(
+                        "Recovery cannot be completed before epoch {}",
+                        pending_recovery.completable_at
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the account has enough admin keys, the only keys that may
+    /// authorize sensitive operations, to meet the threshold of its policy.
+    fn validate_policy(&self, epoch: u64) -> Result<()> {
+        let admin_keys = self
+            .valid_keys
+            .iter()
+            .filter(|k| !k.is_expired(epoch) && k.role == KeyRole::Admin)
+            .count();
+        if admin_keys < self.policy.threshold as usize {
+            return Err(anyhow!(
+                "Policy threshold {} exceeds the number of admin keys ({})",
+                self.policy.threshold,
+                admin_keys
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_key_count(&self, params: &ProtocolParams) -> Result<()> {
+        if self.valid_keys.len() + self.encryption_keys.len()
+            >= params.max_keys_per_account as usize
+        {
+            return Err(anyhow!(
+                "Account already has the maximum of {} keys",
+                params.max_keys_per_account
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_key_expiry(valid_until: Option<u64>, epoch: u64) -> Result<()> {
+        if valid_until.is_some_and(|valid_until| valid_until < epoch) {
+            return Err(anyhow!("Key would already be expired"));
         }
         Ok(())
     }
 
     /// Processes an operation, updating the account state. Should only be run
     /// in the context of a transaction.
-    fn process_operation(&mut self, operation: &Operation) -> Result<()> {
-        self.validate_operation(operation)?;
+    fn process_operation(
+        &mut self,
+        tx: &Transaction,
+        operation: &Operation,
+        ctx: &ValidationContext,
+    ) -> Result<()> {
+        self.validate_operation(tx, operation, ctx)?;
 
         match operation {
-            Operation::AddKey { key } => {
-                self.valid_keys.push(key.clone());
+            Operation::AddKey {
+                key,
+                valid_until,
+                role,
+            } => {
+                self.valid_keys.push(AccountKey::with_role(key.clone(), *valid_until, *role));
             }
             Operation::RevokeKey { key } => {
-                self.valid_keys.retain(|k| k != key);
+                self.valid_keys.retain(|k| &k.key != key);
+            }
+            Operation::AddEncryptionKey { key } => {
+                self.encryption_keys.push(key.clone());
+            }
+            Operation::RevokeEncryptionKey { key } => {
+                self.encryption_keys.retain(|k| k != key);
             }
             Operation::AddData {
                 data,
@@ -195,18 +797,88 @@ impl Account {
                     data: data.clone(),
                 }];
             }
-            Operation::CreateAccount { id, key, .. } => {
+            Operation::RemoveData { selector } => match selector {
+                DataSelector::Index(index) => {
+                    self.signed_data.remove(*index as usize);
+                }
+                DataSelector::Digest(digest) => {
+                    self.signed_data.retain(|entry| &entry.digest() != digest);
+                }
+            },
+            Operation::CreateAccount {
+                id,
+                service_id,
+                key,
+                valid_until,
+                policy,
+                delegate_to_service,
+                ..
+            } => {
                 self.id = id.clone();
-                self.valid_keys.push(key.clone());
+                self.valid_keys.push(AccountKey::new(key.clone(), *valid_until));
+                self.policy = policy.clone();
+                if *delegate_to_service {
+                    self.delegate = Some(service_id.clone());
+                }
             }
             Operation::RegisterService {
                 id,
                 creation_gate,
                 key,
+                policy,
             } => {
                 self.id = id.clone();
-                self.valid_keys.push(key.clone());
+                self.valid_keys.push(AccountKey::new(key.clone(), None));
                 self.service_challenge = Some(creation_gate.clone());
+                self.policy = policy.clone();
+            }
+            Operation::UpdateServiceChallenge { challenge } => {
+                self.service_challenge = Some(challenge.clone());
+            }
+            Operation::SetPolicy { policy } => {
+                self.policy = policy.clone();
+            }
+            Operation::SetRecovery { recovery } => {
+                self.recovery = recovery.clone();
+                self.pending_recovery = None;
+            }
+            Operation::InitiateRecovery { key } => {
+                let Some(recovery) = &self.recovery else {
+                    return Err(anyhow!("Account has no recovery keys"));
+                };
+                self.pending_recovery = Some(PendingRecovery {
+                    key: key.clone(),
+                    completable_at: ctx.epoch.saturating_add(recovery.delay),
+                });
+            }
+            Operation::CancelRecovery => {
+                self.pending_recovery = None;
+            }
+            Operation::RevokeDelegation => {
+                self.delegate = None;
+            }
+            Operation::CompleteRecovery => {
+                let Some(pending_recovery) = self.pending_recovery.take() else {
+                    return Err(anyhow!("No recovery in progress"));
+                };
+                self.valid_keys = vec![AccountKey::new(pending_recovery.key, None)];
+                // The recovered key is the only key left to sign with
+                self.policy = AccountPolicy::default();
+                // The service could otherwise replace the recovered key
+                self.delegate = None;
+                // The owner lost access to the old keys, so data should no
+                // longer be encrypted for the old encryption keys either
+                self.encryption_keys.clear();
+            }
+            Operation::DeactivateAccount => {
+                self.valid_keys.clear();
+                self.encryption_keys.clear();
+                self.signed_data.clear();
+                self.service_challenge = None;
+                self.recovery = None;
+                self.pending_recovery = None;
+                self.delegate = None;
+                self.deactivated = true;
             }
         }
 
@@ -0,0 +1,99 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::digest::Digest;
+
+// Domain separation between leaves and inner nodes prevents an inner node
+// from being presented as an allowed id.
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// A binary Merkle tree over the account ids permitted by a
+/// [`crate::operation::ServiceChallenge::AllowList`]. Only its root is stored
+/// on Prism, the service keeps the full list to hand out inclusion proofs.
+pub struct AllowList {
+    ids: Vec<String>,
+    levels: Vec<Vec<Digest>>,
+}
+
+impl AllowList {
+    pub fn new(ids: Vec<String>) -> Self {
+        let mut levels = vec![ids.iter().map(|id| leaf_hash(id)).collect::<Vec<_>>()];
+
+        while levels.last().is_some_and(|level| level.len() > 1) {
+            let level = levels.last().unwrap();
+            let next = level
+                .chunks(2)
+                .map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+                .collect();
+            levels.push(next);
+        }
+
+        AllowList { ids, levels }
+    }
+
+    /// Returns the root committing to all ids in the list. An empty list has
+    /// the zero digest as root, which no proof verifies against.
+    pub fn root(&self) -> Digest {
+        self.levels.last().and_then(|level| level.first()).copied().unwrap_or(Digest::zero())
+    }
+
+    /// Creates an inclusion proof for the given id, if it is part of the list.
+    pub fn prove(&self, id: &str) -> Option<AllowListProof> {
+        let index = self.ids.iter().position(|allowed| allowed == id)?;
+
+        let mut siblings = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = level.get(position ^ 1).unwrap_or(&level[position]);
+            siblings.push(*sibling);
+            position /= 2;
+        }
+
+        Some(AllowListProof {
+            index: index as u64,
+            siblings,
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+/// Proof that an id is included in an [`AllowList`].
+pub struct AllowListProof {
+    /// Position of the id in the allow list
+    #[schema(example = 3)]
+    pub index: u64,
+    /// Sibling hashes on the path from the id to the root, starting at the leaf
+    pub siblings: Vec<Digest>,
+}
+
+impl AllowListProof {
+    /// Verifies that the id is included in the allow list with the given root.
+    pub fn verify(&self, root: &Digest, id: &str) -> Result<()> {
+        let mut node = leaf_hash(id);
+        let mut position = self.index;
+
+        for sibling in &self.siblings {
+            node = if position & 1 == 0 {
+                node_hash(&node, sibling)
+            } else {
+                node_hash(sibling, &node)
+            };
+            position /= 2;
+        }
+
+        if position != 0 || &node != root {
+            bail!("Id {} is not included in allow list", id);
+        }
+        Ok(())
+    }
+}
+
+fn leaf_hash(id: &str) -> Digest {
+    Digest::hash_items(&[LEAF_PREFIX, id.as_bytes()])
+}
+
+fn node_hash(left: &Digest, right: &Digest) -> Digest {
+    Digest::hash_items(&[NODE_PREFIX, left.as_bytes(), right.as_bytes()])
+}
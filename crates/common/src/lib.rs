@@ -1,6 +1,8 @@
 pub mod account;
+pub mod allow_list;
 pub mod digest;
 pub mod operation;
+pub mod params;
 pub mod transaction;
 
 #[cfg(feature = "test_utils")]
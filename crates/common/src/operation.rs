@@ -1,12 +1,19 @@
-use anyhow::{bail, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 
 use serde::{Deserialize, Serialize};
 use std::{self, fmt::Display};
 use utoipa::ToSchema;
 
-use prism_keys::{Signature, SigningKey, VerifyingKey};
+use prism_keys::{EncryptionKey, PopVerifiedKey, Signature, SigningKey, VerifyingKey};
 use prism_serde::raw_or_b64;
 
+use crate::{
+    account::{AccountPolicy, KeyRole, RecoveryConfig},
+    allow_list::AllowListProof,
+    digest::Digest,
+    params::{ProtocolParams, ValidationContext},
+};
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
 #[schema(
     title = "Operation",
@@ -37,6 +44,17 @@ pub enum Operation {
         challenge: ServiceChallengeInput,
         /// Public key associated with the account
         key: VerifyingKey,
+        /// Last epoch in which the key may be used. Never expires if omitted.
+        #[serde(default)]
+        #[schema(example = 42)]
+        valid_until: Option<u64>,
+        /// Authorization policy of the account. Defaults to a single signature.
+        #[serde(default)]
+        policy: AccountPolicy,
+        /// Allows the service to add and revoke keys of the account on its
+        /// behalf, e.g. to restore access after an out-of-band identity check.
+        #[serde(default)]
+        delegate_to_service: bool,
     },
     #[schema(title = "RegisterService")]
     /// Registers a new service with the given id.
@@ -48,6 +66,16 @@ pub enum Operation {
         creation_gate: ServiceChallenge,
         /// Public key associated with the service
         key: VerifyingKey,
+        /// Authorization policy of the service account. Defaults to a single signature.
+        #[serde(default)]
+        policy: AccountPolicy,
+    },
+    #[schema(title = "UpdateServiceChallenge")]
+    /// Replaces the challenge of an existing service. Accounts created for the
+    /// service afterwards need to meet the new challenge.
+    UpdateServiceChallenge {
+        /// The new challenge that defines how accounts can be created for this service
+        challenge: ServiceChallenge,
     },
     #[schema(title = "AddData")]
     /// Adds arbitrary signed data to an existing account.
@@ -69,11 +97,24 @@ pub enum Operation {
         /// Bundle containing signature of the data and verification key
         data_signature: SignatureBundle,
     },
+    #[schema(title = "RemoveData")]
+    /// Removes individual signed data entries from an existing account.
+    RemoveData {
+        /// The entries to be removed
+        selector: DataSelector,
+    },
     #[schema(title = "AddKey")]
     /// Adds a key to an existing account.
     AddKey {
         /// Public key to be added to the account
         key: VerifyingKey,
+        /// Last epoch in which the key may be used. Never expires if omitted.
+        #[serde(default)]
+        #[schema(example = 42)]
+        valid_until: Option<u64>,
+        /// The operations the key may authorize. Defaults to all of them.
+        #[serde(default)]
+        role: KeyRole,
     },
     #[schema(title = "RevokeKey")]
     /// Revokes a key from an existing account.
@@ -81,6 +122,54 @@ pub enum Operation {
         /// Public key to be revoked from the account
         key: VerifyingKey,
     },
+    #[schema(title = "AddEncryptionKey")]
+    /// Publishes a key that data can be encrypted for on an existing account.
+    /// Encryption keys cannot sign transactions.
+    AddEncryptionKey {
+        /// Public encryption key to be added to the account
+        key: EncryptionKey,
+    },
+    #[schema(title = "RevokeEncryptionKey")]
+    /// Revokes an encryption key from an existing account.
+    RevokeEncryptionKey {
+        /// Public encryption key to be revoked from the account
+        key: EncryptionKey,
+    },
+    #[schema(title = "SetPolicy")]
+    /// Replaces the authorization policy of an existing account.
+    SetPolicy {
+        /// The new policy of the account
+        policy: AccountPolicy,
+    },
+    #[schema(title = "SetRecovery")]
+    /// Sets or removes the recovery keys of an existing account. Aborts a
+    /// pending recovery.
+    SetRecovery {
+        /// The new recovery configuration, or None to disable recovery
+        recovery: Option<RecoveryConfig>,
+    },
+    #[schema(title = "InitiateRecovery")]
+    /// Starts replacing all keys of an account with a new key. Must be signed
+    /// by one of the account's recovery keys.
+    InitiateRecovery {
+        /// Public key that will be the only key of the account once recovered
+        key: VerifyingKey,
+    },
+    #[schema(title = "CancelRecovery")]
+    /// Aborts a pending recovery. Can be signed by any active account key.
+    CancelRecovery,
+    #[schema(title = "RevokeDelegation")]
+    /// Withdraws the authority of the account's service to add and revoke its
+    /// keys.
+    RevokeDelegation,
+    #[schema(title = "CompleteRecovery")]
+    /// Completes a pending recovery once its delay has passed. Must be signed
+    /// by one of the account's recovery keys.
+    CompleteRecovery,
+    #[schema(title = "DeactivateAccount")]
+    /// Permanently deactivates an account, removing all of its keys and data.
+    /// The id stays reserved and cannot be used to create a new account.
+    DeactivateAccount,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
@@ -92,6 +181,17 @@ pub struct SignatureBundle {
     pub signature: Signature,
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+/// Selects entries of an account's signed data.
+pub enum DataSelector {
+    /// The entry at the given position
+    #[schema(title = "Index")]
+    Index(u64),
+    /// All entries whose data hashes to the given digest
+    #[schema(title = "Digest")]
+    Digest(Digest),
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
 /// Input required to complete a challenge for account creation.
 pub enum ServiceChallengeInput {
@@ -99,6 +199,27 @@ pub enum ServiceChallengeInput {
     /// The provided signature will be verified using the corresponding key from the challenge.
     #[schema(title = "Signed")]
     Signed(Signature),
+    /// Input when meeting `ServiceChallenge::Open`. Carries no data.
+    #[schema(title = "Open")]
+    Open,
+    /// Input required when meeting `ServiceChallenge::AllowList`.
+    /// Proves that the account id is part of the service's allow list.
+    #[schema(title = "AllowList")]
+    AllowList(AllowListProof),
+    /// Input required when meeting `ServiceChallenge::Threshold`.
+    /// Signatures by distinct keys of the challenge.
+    #[schema(title = "Threshold")]
+    Threshold(Vec<SignatureBundle>),
+    /// Input required when meeting `ServiceChallenge::AggregateThreshold`.
+    /// A single signature aggregated from the signatures of distinct keys of
+    /// the challenge.
+    #[schema(title = "AggregateThreshold")]
+    AggregateThreshold {
+        /// Indices of the signing keys in the challenge
+        signers: Vec<u32>,
+        /// Aggregate of the signatures of all signers
+        signature: Signature,
+    },
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
@@ -109,6 +230,35 @@ pub enum ServiceChallenge {
     /// such that the given key can be used to verify their signatures.
     #[schema(title = "Signed")]
     Signed(VerifyingKey),
+    /// Challenge that allows anyone to create accounts for the service.
+    #[schema(title = "Open")]
+    Open,
+    /// Challenge that only allows creating accounts whose id is included in
+    /// the allow list with the given Merkle root.
+    #[schema(title = "AllowList")]
+    AllowList(Digest),
+    /// Challenge that requires `threshold` of the given keys to sign
+    /// corresponding CreateAccount operations.
+    #[schema(title = "Threshold")]
+    Threshold {
+        /// Keys that may sign account creations
+        keys: Vec<VerifyingKey>,
+        /// Number of distinct keys that need to sign
+        #[schema(example = 2)]
+        threshold: u32,
+    },
+    /// Challenge that requires `threshold` of the given BLS12-381 keys to sign
+    /// corresponding CreateAccount operations with one aggregate signature, so
+    /// that account creations don't grow with the threshold.
+    #[schema(title = "AggregateThreshold")]
+    AggregateThreshold {
+        /// Keys that may sign account creations, along with their proofs of
+        /// possession
+        keys: Vec<PopVerifiedKey>,
+        /// Number of distinct keys that need to sign
+        #[schema(example = 2)]
+        threshold: u32,
+    },
 }
 
 impl From<SigningKey> for ServiceChallenge {
@@ -117,27 +267,228 @@ impl From<SigningKey> for ServiceChallenge {
     }
 }
 
+impl ServiceChallenge {
+    /// Returns the digest that the service signs to approve the creation of
+    /// an account with the given id and key.
+    pub fn credentials_digest(id: &str, service_id: &str, key: &VerifyingKey) -> Digest {
+        Digest::hash_items(&[id.as_bytes(), service_id.as_bytes(), &key.to_bytes()])
+    }
+
+    pub fn validate_basic(&self, params: &ProtocolParams) -> Result<()> {
+        match self {
+            ServiceChallenge::Signed(key) => params.validate_key(key),
+            ServiceChallenge::Threshold { keys, threshold } => {
+                Self::validate_threshold(keys.len(), *threshold, params)?;
+                keys.iter().try_for_each(|key| params.validate_key(key))
+            }
+            ServiceChallenge::AggregateThreshold { keys, threshold } => {
+                Self::validate_threshold(keys.len(), *threshold, params)?;
+                for (i, key) in keys.iter().enumerate() {
+                    // A duplicate key would count twice towards the threshold
+                    if keys[..i].iter().any(|other| other.verifying_key() == key.verifying_key()) {
+                        bail!("Challenge contains duplicate keys");
+                    }
+                    params.validate_key(key.verifying_key())?;
+                }
+                Ok(())
+            }
+            ServiceChallenge::Open | ServiceChallenge::AllowList(_) => Ok(()),
+        }
+    }
+
+    fn validate_threshold(key_count: usize, threshold: u32, params: &ProtocolParams) -> Result<()> {
+        if threshold == 0 || threshold as usize > key_count {
+            bail!(
+                "threshold must be between 1 and the number of keys ({}), got {}",
+                key_count,
+                threshold
+            );
+        }
+        if key_count > params.max_keys_per_account as usize {
+            bail!(
+                "Challenge has {} keys, maximum is {}",
+                key_count,
+                params.max_keys_per_account
+            );
+        }
+        Ok(())
+    }
+
+    /// Verifies that the input meets the challenge for creating an account
+    /// with the given id and key.
+    pub fn verify_input(
+        &self,
+        input: &ServiceChallengeInput,
+        id: &str,
+        service_id: &str,
+        key: &VerifyingKey,
+        ctx: &ValidationContext,
+    ) -> Result<()> {
+        match (self, input) {
+            (ServiceChallenge::Signed(challenge_vk), ServiceChallengeInput::Signed(signature)) => {
+                let hash = Self::credentials_digest(id, service_id, key);
+                ctx.verify_signature(challenge_vk, &hash.to_bytes(), signature)
+            }
+            (ServiceChallenge::Open, ServiceChallengeInput::Open) => Ok(()),
+            (ServiceChallenge::AllowList(root), ServiceChallengeInput::AllowList(proof)) => {
+                proof.verify(root, id)
+            }
+            (
+                ServiceChallenge::Threshold { keys, threshold },
+                ServiceChallengeInput::Threshold(signatures),
+            ) => {
+                let hash = Self::credentials_digest(id, service_id, key);
+                for (i, bundle) in signatures.iter().enumerate() {
+                    if !keys.contains(&bundle.verifying_key) {
+                        bail!("Challenge signature by unknown key");
+                    }
+                    if signatures[..i]
+                        .iter()
+                        .any(|other| other.verifying_key == bundle.verifying_key)
+                    {
+                        bail!("Duplicate challenge signature");
+                    }
+                    ctx.verify_signature(
+                        &bundle.verifying_key,
+                        &hash.to_bytes(),
+                        &bundle.signature,
+                    )?;
+                }
+                ensure!(
+                    signatures.len() >= *threshold as usize,
+                    "Challenge requires {} signatures, got {}",
+                    threshold,
+                    signatures.len()
+                );
+                Ok(())
+            }
+            (
+                ServiceChallenge::AggregateThreshold { keys, threshold },
+                ServiceChallengeInput::AggregateThreshold { signers, signature },
+            ) => {
+                let mut signing_keys = Vec::with_capacity(signers.len());
+                for (i, index) in signers.iter().enumerate() {
+                    if signers[..i].contains(index) {
+                        bail!("Duplicate challenge signature");
+                    }
+                    let key = keys
+                        .get(*index as usize)
+                        .ok_or_else(|| anyhow!("Challenge signature by unknown key"))?;
+                    signing_keys.push(key.clone());
+                }
+                ensure!(
+                    signers.len() >= *threshold as usize,
+                    "Challenge requires {} signatures, got {}",
+                    threshold,
+                    signers.len()
+                );
+                let hash = Self::credentials_digest(id, service_id, key);
+                VerifyingKey::verify_aggregate(&signing_keys, &hash.to_bytes(), signature)
+            }
+            _ => bail!("Challenge input does not match service challenge"),
+        }
+    }
+}
+
 impl Operation {
     pub fn get_public_key(&self) -> Option<&VerifyingKey> {
         match self {
             Operation::RevokeKey { key }
-            | Operation::AddKey { key }
+            | Operation::AddKey { key, .. }
             | Operation::CreateAccount { key, .. }
-            | Operation::RegisterService { key, .. } => Some(key),
-            Operation::AddData { .. } | Operation::SetData { .. } => None,
+            | Operation::RegisterService { key, .. }
+            | Operation::InitiateRecovery { key } => Some(key),
+            Operation::AddData { .. }
+            | Operation::SetData { .. }
+            | Operation::RemoveData { .. }
+            | Operation::AddEncryptionKey { .. }
+            | Operation::RevokeEncryptionKey { .. }
+            | Operation::UpdateServiceChallenge { .. }
+            | Operation::SetPolicy { .. }
+            | Operation::SetRecovery { .. }
+            | Operation::CancelRecovery
+            | Operation::CompleteRecovery
+            | Operation::RevokeDelegation
+            | Operation::DeactivateAccount => None,
         }
     }
 
-    pub fn validate_basic(&self) -> Result<()> {
+    /// Returns true if the operation creates a new account. Such operations
+    /// can only be the first operation of a transaction.
+    pub fn is_creation(&self) -> bool {
+        matches!(
+            self,
+            Operation::CreateAccount { .. } | Operation::RegisterService { .. }
+        )
+    }
+
+    /// Returns true if the operation is signed by a recovery key instead of
+    /// the account's keys. Such operations can't be combined with others.
+    pub fn is_recovery(&self) -> bool {
+        matches!(
+            self,
+            Operation::InitiateRecovery { .. } | Operation::CompleteRecovery
+        )
+    }
+
+    /// Returns true if the operation can be signed by the keys of the
+    /// account's service instead, if the account has delegated to it.
+    pub fn is_delegable(&self) -> bool {
+        matches!(
+            self,
+            Operation::AddKey { .. }
+                | Operation::RevokeKey { .. }
+                | Operation::AddEncryptionKey { .. }
+                | Operation::RevokeEncryptionKey { .. }
+        )
+    }
+
+    /// Returns true if the operation changes who is able to act on behalf of
+    /// the account or its service. These operations need to be signed by as
+    /// many keys as the account's [`AccountPolicy`] requires.
+    pub fn is_sensitive(&self) -> bool {
+        matches!(
+            self,
+            Operation::AddKey { .. }
+                | Operation::RevokeKey { .. }
+                | Operation::AddEncryptionKey { .. }
+                | Operation::RevokeEncryptionKey { .. }
+                | Operation::SetPolicy { .. }
+                | Operation::SetRecovery { .. }
+                | Operation::UpdateServiceChallenge { .. }
+                | Operation::RevokeDelegation
+                | Operation::DeactivateAccount
+        )
+    }
+
+    /// Validates the operation on its own, without considering the account it
+    /// is applied to, against the limits of the network.
+    pub fn validate_basic(&self, params: &ProtocolParams) -> Result<()> {
+        if let Some(key) = self.get_public_key() {
+            params.validate_key(key)?;
+        }
+
         match &self {
-            Operation::RegisterService { id, .. } => {
+            Operation::RegisterService {
+                id,
+                creation_gate,
+                policy,
+                ..
+            } => {
                 if id.is_empty() {
                     bail!("id must not be empty when registering service");
                 }
+                params.validate_id(id)?;
 
-                Ok(())
+                creation_gate.validate_basic(params)?;
+                policy.validate_basic()
             }
-            Operation::CreateAccount { id, service_id, .. } => {
+            Operation::CreateAccount {
+                id,
+                service_id,
+                policy,
+                ..
+            } => {
                 if id.is_empty() {
                     bail!("id must not be empty when creating account service");
                 }
@@ -145,15 +496,28 @@ impl Operation {
                 if service_id.is_empty() {
                     bail!("service_id must not be empty when creating account service");
                 }
+                params.validate_id(id)?;
+                params.validate_id(service_id)?;
 
-                Ok(())
+                policy.validate_basic()
+            }
+            Operation::AddKey { .. }
+            | Operation::RevokeKey { .. }
+            | Operation::RevokeEncryptionKey { .. }
+            | Operation::RemoveData { .. } => Ok(()),
+            Operation::AddEncryptionKey { key } => params.validate_encryption_key(key),
+            Operation::UpdateServiceChallenge { challenge } => challenge.validate_basic(params),
+            Operation::SetPolicy { policy } => policy.validate_basic(),
+            Operation::SetRecovery { recovery } => {
+                recovery.as_ref().map_or(Ok(()), |recovery| recovery.validate_basic(params))
             }
-            Operation::AddKey { .. } | Operation::RevokeKey { .. } => Ok(()),
+            Operation::InitiateRecovery { .. }
+            | Operation::CancelRecovery
+            | Operation::CompleteRecovery
+            | Operation::RevokeDelegation
+            | Operation::DeactivateAccount => Ok(()),
             Operation::AddData { data, .. } | Operation::SetData { data, .. } => {
-                let data_len = data.len();
-                // TODO determine proper max data size here
-                ensure!(data_len < usize::MAX, "Incoming data size is {}", data_len);
-                Ok(())
+                params.validate_data(data)
             }
         }
     }
@@ -0,0 +1,184 @@
+use anyhow::{anyhow, bail, Result};
+use prism_keys::{BatchVerifier, CryptoAlgorithm, EncryptionKey, Signature, VerifyingKey};
+use prism_serde::binary::ToBinary;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use utoipa::ToSchema;
+
+use crate::digest::Digest;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+/// Parameters that every transaction on a network has to respect. They are
+/// fixed per network and committed to by every epoch proof, so that provers
+/// cannot change them without light clients noticing.
+pub struct ProtocolParams {
+    /// Identifier of the network. Transactions and epochs are signed for a
+    /// specific chain and cannot be replayed on other networks.
+    #[schema(example = "specter")]
+    pub chain_id: String,
+    /// Maximum size in bytes of data added to an account in a single operation
+    #[schema(example = 65536)]
+    pub max_data_size: u64,
+    /// Maximum number of keys an account can hold at once, including
+    /// encryption keys
+    #[schema(example = 32)]
+    pub max_keys_per_account: u32,
+    /// Maximum number of signed data entries an account can hold at once
+    #[schema(example = 128)]
+    pub max_signed_data_entries: u32,
+    /// Maximum length in bytes of account and service ids
+    #[schema(example = 256)]
+    pub max_id_length: u32,
+    /// Algorithms that keys stored in accounts may use, both for signing and
+    /// encryption keys
+    pub allowed_algorithms: Vec<CryptoAlgorithm>,
+}
+
+impl Default for ProtocolParams {
+    fn default() -> Self {
+        ProtocolParams {
+            chain_id: "custom".to_string(),
+            max_data_size: 64 * 1024,
+            max_keys_per_account: 32,
+            max_signed_data_entries: 128,
+            max_id_length: 256,
+            allowed_algorithms: vec![
+                CryptoAlgorithm::Ed25519,
+                CryptoAlgorithm::Secp256k1,
+                CryptoAlgorithm::Secp256r1,
+                CryptoAlgorithm::Ethereum,
+                CryptoAlgorithm::Bls12381,
+                CryptoAlgorithm::MlDsa65,
+                CryptoAlgorithm::X25519,
+                CryptoAlgorithm::Secp256r1Ecdh,
+            ],
+        }
+    }
+}
+
+impl ProtocolParams {
+    /// Creates the default parameters for the chain with the given id.
+    pub fn for_chain(chain_id: &str) -> Self {
+        ProtocolParams {
+            chain_id: chain_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the digest of the parameters that is committed to by the SP1
+    /// program.
+    pub fn digest(&self) -> Result<Digest> {
+        Ok(Digest::hash(self.encode_to_bytes()?))
+    }
+
+    pub fn validate_id(&self, id: &str) -> Result<()> {
+        if id.len() > self.max_id_length as usize {
+            bail!(
+                "id is {} bytes long, maximum is {}",
+                id.len(),
+                self.max_id_length
+            );
+        }
+        Ok(())
+    }
+
+    pub fn validate_data(&self, data: &[u8]) -> Result<()> {
+        if data.len() as u64 > self.max_data_size {
+            bail!(
+                "data is {} bytes long, maximum is {}",
+                data.len(),
+                self.max_data_size
+            );
+        }
+        Ok(())
+    }
+
+    pub fn validate_key(&self, key: &VerifyingKey) -> Result<()> {
+        if !self.allowed_algorithms.contains(&key.algorithm()) {
+            bail!("{:?} keys are not allowed", key.algorithm());
+        }
+        Ok(())
+    }
+
+    pub fn validate_encryption_key(&self, key: &EncryptionKey) -> Result<()> {
+        if !self.allowed_algorithms.contains(&key.algorithm()) {
+            bail!("{:?} keys are not allowed", key.algorithm());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+/// Everything apart from the account state that transactions are validated
+/// against.
+pub struct ValidationContext {
+    /// The epoch the transaction is applied in
+    pub epoch: u64,
+    /// The parameters of the network
+    pub params: ProtocolParams,
+    /// Collects signatures instead of verifying them immediately, if set.
+    /// See [`ValidationContext::with_batch_verification`].
+    #[serde(skip)]
+    batch: Option<Arc<Mutex<BatchVerifier>>>,
+}
+
+impl PartialEq for ValidationContext {
+    fn eq(&self, other: &Self) -> bool {
+        self.epoch == other.epoch && self.params == other.params
+    }
+}
+
+impl Eq for ValidationContext {}
+
+impl ValidationContext {
+    pub fn new(epoch: u64, params: ProtocolParams) -> Self {
+        ValidationContext {
+            epoch,
+            params,
+            batch: None,
+        }
+    }
+
+    /// Creates a context for the given epoch with default parameters.
+    pub fn at_epoch(epoch: u64) -> Self {
+        ValidationContext {
+            epoch,
+            ..Default::default()
+        }
+    }
+
+    /// Defers the verification of signatures until
+    /// [`ValidationContext::verify_batch`] is called, so that they can be
+    /// verified at once. Until then, transactions may be accepted even though
+    /// their signatures are invalid.
+    pub fn with_batch_verification(mut self) -> Self {
+        self.batch = Some(Arc::new(Mutex::new(BatchVerifier::new())));
+        self
+    }
+
+    /// Verifies the signature of the message, or queues it for verification
+    /// if batch verification is enabled.
+    pub fn verify_signature(
+        &self,
+        vk: &VerifyingKey,
+        message: &[u8],
+        signature: &Signature,
+    ) -> Result<()> {
+        match &self.batch {
+            Some(batch) => batch
+                .lock()
+                .map_err(|_| anyhow!("Batch verifier is poisoned"))?
+                .queue(vk, message, signature),
+            None => vk.verify_signature(message, signature),
+        }
+    }
+
+    /// Verifies all signatures queued since batch verification was enabled.
+    pub fn verify_batch(&self) -> Result<()> {
+        let Some(batch) = &self.batch else {
+            return Ok(());
+        };
+        let mut batch = batch.lock().map_err(|_| anyhow!("Batch verifier is poisoned"))?;
+        std::mem::take(&mut *batch).verify()
+    }
+}
@@ -1,19 +1,20 @@
 use anyhow::{anyhow, Result};
 use celestia_types::Blob;
-use prism_keys::{Signature, SigningKey, VerifyingKey};
+use prism_keys::{Signature, Signer, VerifyingKey};
 use prism_serde::binary::{FromBinary, ToBinary};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::operation::Operation;
+use crate::operation::{Operation, SignatureBundle};
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
 /// Represents a prism transaction that can be applied to an account.
 pub struct Transaction {
     /// The account id that this transaction is for
     pub id: String,
-    /// The [`Operation`] to be applied to the account
-    pub operation: Operation,
+    /// The [`Operation`]s to be applied to the account, in order. Either all
+    /// of them are applied or none.
+    pub operations: Vec<Operation>,
     /// The nonce of the account at the time of this transaction
     pub nonce: u64,
     /// The signature of the transaction, signed by [`self::vk`].
@@ -21,27 +22,69 @@ pub struct Transaction {
     /// The verifying key of the signer of this transaction. This vk must be
     /// included in the account's valid_keys set.
     pub vk: VerifyingKey,
+    /// Additional signatures over the same payload by other keys of the
+    /// account, required by sensitive operations on accounts with a
+    /// threshold policy.
+    #[serde(default)]
+    pub cosignatures: Vec<SignatureBundle>,
+    /// Whether the transaction is signed by keys of the service the account
+    /// has delegated to, instead of the account's own keys.
+    #[serde(default)]
+    pub delegated: bool,
+    /// The last epoch in which the transaction may be applied. A transaction
+    /// that is not included in a batch by then is rejected and has to be
+    /// resubmitted. Does not expire if unset.
+    #[serde(default)]
+    #[schema(example = 142)]
+    pub valid_until: Option<u64>,
 }
 
 impl Transaction {
-    /// Encodes the transaction to bytes to prepare for signing.
-    pub fn get_signature_payload(&self) -> Result<Vec<u8>> {
+    /// Encodes the transaction to bytes to prepare for signing. The payload
+    /// is bound to the given chain, so that signed transactions cannot be
+    /// replayed on other networks.
+    pub fn get_signature_payload(&self, chain_id: &str) -> Result<Vec<u8>> {
         let mut tx = self.clone();
         tx.signature = Signature::Placeholder;
-        tx.encode_to_bytes().map_err(|e| anyhow!(e))
+        tx.cosignatures.clear();
+        (chain_id, tx).encode_to_bytes().map_err(|e| anyhow!(e))
     }
 
-    /// Signs the transaction with the given [`SigningKey`] and inserts the
-    /// signature into the transaction.
-    pub fn sign(&mut self, sk: &SigningKey) -> Result<Signature> {
+    /// Signs the transaction for the given chain with the given [`Signer`]
+    /// and inserts the signature into the transaction.
+    pub fn sign<S: Signer + ?Sized>(&mut self, signer: &S, chain_id: &str) -> Result<Signature> {
         if let Signature::Placeholder = self.signature {
-            let sig = sk.sign(&self.get_signature_payload()?);
+            let sig = signer.sign(&self.get_signature_payload(chain_id)?)?;
             self.signature = sig.clone();
             Ok(sig)
         } else {
             Err(anyhow!("Transaction already signed"))
         }
     }
+
+    /// Adds a signature of another account key to the transaction. The
+    /// cosignature covers the same payload as the primary signature.
+    pub fn cosign<S: Signer + ?Sized>(&mut self, signer: &S, chain_id: &str) -> Result<Signature> {
+        let verifying_key = signer.verifying_key();
+        if verifying_key == self.vk
+            || self.cosignatures.iter().any(|bundle| bundle.verifying_key == verifying_key)
+        {
+            return Err(anyhow!("Transaction already signed by this key"));
+        }
+
+        let sig = signer.sign(&self.get_signature_payload(chain_id)?)?;
+        self.cosignatures.push(SignatureBundle {
+            verifying_key,
+            signature: sig.clone(),
+        });
+        Ok(sig)
+    }
+
+    /// Returns the verifying keys of all signers of the transaction, starting
+    /// with the primary signer.
+    pub fn signers(&self) -> impl Iterator<Item = &VerifyingKey> {
+        std::iter::once(&self.vk).chain(self.cosignatures.iter().map(|b| &b.verifying_key))
+    }
 }
 
 impl TryFrom<&Blob> for Transaction {
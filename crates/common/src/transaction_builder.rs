@@ -1,17 +1,21 @@
 use std::collections::HashMap;
 
 use crate::{
-    account::Account,
+    account::{Account, AccountPolicy, KeyRole, RecoveryConfig},
     digest::Digest,
-    operation::{Operation, ServiceChallenge, ServiceChallengeInput, SignatureBundle},
+    operation::{
+        DataSelector, Operation, ServiceChallenge, ServiceChallengeInput, SignatureBundle,
+    },
+    params::{ProtocolParams, ValidationContext},
     transaction::Transaction,
 };
-use prism_keys::{CryptoAlgorithm, SigningKey, VerifyingKey};
+use prism_keys::{CryptoAlgorithm, EncryptionKey, Signature, SigningKey, VerifyingKey};
 enum PostCommitAction {
     UpdateStorageOnly,
     RememberServiceKey(String, SigningKey),
     RememberAccountKey(String, SigningKey),
     RemoveAccountKey(String, VerifyingKey),
+    ForgetAccountKeys(String),
 }
 
 pub struct UncommittedTransaction<'a> {
@@ -24,10 +28,19 @@ impl UncommittedTransaction<'_> {
     /// Commits and returns a transaction, updating the builder. Subsequent transactions
     /// built with the same builder will have the correct previous hash.
     pub fn commit(self) -> Transaction {
-        let acc = self.builder.accounts.entry(self.transaction.id.clone()).or_default();
-
-        acc.process_transaction(&self.transaction)
-            .expect("Adding transaction entry to account should work");
+        let mut acc = self.builder.accounts.get(&self.transaction.id).cloned().unwrap_or_default();
+        if self.transaction.delegated {
+            let service = acc
+                .delegate()
+                .and_then(|service_id| self.builder.accounts.get(service_id))
+                .expect("Delegate service should exist");
+            acc.process_delegated_transaction(&self.transaction, service, &self.builder.context)
+                .expect("Adding delegated transaction entry to account should work");
+        } else {
+            acc.process_transaction(&self.transaction, &self.builder.context)
+                .expect("Adding transaction entry to account should work");
+        }
+        self.builder.accounts.insert(self.transaction.id.clone(), acc);
 
         match self.post_commit_action {
             PostCommitAction::UpdateStorageOnly => (),
@@ -42,6 +55,9 @@ impl UncommittedTransaction<'_> {
                     }
                 }
             }
+            PostCommitAction::ForgetAccountKeys(id) => {
+                self.builder.account_keys.remove(&id);
+            }
             PostCommitAction::RememberServiceKey(id, service_key) => {
                 self.builder.service_keys.insert(id, service_key);
             }
@@ -50,6 +66,14 @@ impl UncommittedTransaction<'_> {
         self.transaction
     }
 
+    /// Adds a cosignature of another account key to the transaction.
+    pub fn cosign(mut self, signing_key: &SigningKey) -> Self {
+        self.transaction
+            .cosign(signing_key, &self.builder.context.params.chain_id)
+            .expect("Cosigning transaction should work");
+        self
+    }
+
     /// Returns a transaction without updating the builder.
     /// Can be used to create invalid transactions.
     pub fn build(self) -> Transaction {
@@ -64,6 +88,10 @@ pub struct TransactionBuilder {
     service_keys: HashMap<String, SigningKey>,
     /// Remembers private keys of accounts to simulate actions on behalf of these accounts
     account_keys: HashMap<String, Vec<SigningKey>>,
+    /// Epoch and network parameters that committed transactions are applied with
+    context: ValidationContext,
+    /// Last epoch in which subsequently built transactions may be applied
+    transaction_valid_until: Option<u64>,
 }
 
 impl Default for TransactionBuilder {
@@ -76,6 +104,8 @@ impl Default for TransactionBuilder {
             accounts,
             service_keys,
             account_keys,
+            context: ValidationContext::default(),
+            transaction_valid_until: None,
         }
     }
 }
@@ -101,6 +131,23 @@ impl TransactionBuilder {
         self.accounts.get(id)
     }
 
+    /// Sets the epoch at which subsequently committed transactions are applied.
+    pub fn set_epoch(&mut self, epoch: u64) {
+        self.context.epoch = epoch;
+    }
+
+    /// Sets the last epoch in which subsequently built transactions may be
+    /// applied. `None` creates transactions that do not expire.
+    pub fn set_transaction_valid_until(&mut self, valid_until: Option<u64>) {
+        self.transaction_valid_until = valid_until;
+    }
+
+    /// Sets the network parameters that subsequently committed transactions are
+    /// validated against.
+    pub fn set_params(&mut self, params: ProtocolParams) {
+        self.context.params = params;
+    }
+
     pub fn register_service_with_random_keys(
         &mut self,
         algorithm: CryptoAlgorithm,
@@ -118,24 +165,71 @@ impl TransactionBuilder {
         id: &str,
         challenge_key: SigningKey,
         signing_key: SigningKey,
+    ) -> UncommittedTransaction {
+        let creation_gate = ServiceChallenge::Signed(challenge_key.verifying_key());
+        let post_commit_action =
+            PostCommitAction::RememberServiceKey(id.to_string(), challenge_key);
+        self.register_service_with_gate(id, creation_gate, signing_key, post_commit_action)
+    }
+
+    /// Registers a service with an arbitrary creation gate. Accounts for it
+    /// have to be created via [`Self::create_account_with_challenge_input`].
+    pub fn register_service_with_challenge(
+        &mut self,
+        id: &str,
+        creation_gate: ServiceChallenge,
+        signing_key: SigningKey,
+    ) -> UncommittedTransaction {
+        self.register_service_with_gate(
+            id,
+            creation_gate,
+            signing_key,
+            PostCommitAction::UpdateStorageOnly,
+        )
+    }
+
+    fn register_service_with_gate(
+        &mut self,
+        id: &str,
+        creation_gate: ServiceChallenge,
+        signing_key: SigningKey,
+        post_commit_action: PostCommitAction,
     ) -> UncommittedTransaction {
         let vk: VerifyingKey = signing_key.clone().into();
         let op = Operation::RegisterService {
             id: id.to_string(),
-            creation_gate: ServiceChallenge::Signed(challenge_key.verifying_key()),
+            creation_gate,
             key: vk.clone(),
+            policy: AccountPolicy::default(),
         };
 
         let account = Account::default();
-        let transaction = account.prepare_transaction(id.to_string(), op, &signing_key).unwrap();
+        let transaction = self.prepare_transaction(&account, id, vec![op], &signing_key);
 
         UncommittedTransaction {
             transaction,
             builder: self,
-            post_commit_action: PostCommitAction::RememberServiceKey(id.to_string(), challenge_key),
+            post_commit_action,
         }
     }
 
+    pub fn update_service_challenge(
+        &mut self,
+        id: &str,
+        challenge_key: SigningKey,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let op = Operation::UpdateServiceChallenge {
+            challenge: ServiceChallenge::Signed(challenge_key.verifying_key()),
+        };
+        self.account_operation(
+            id,
+            op,
+            signing_key,
+            PostCommitAction::RememberServiceKey(id.to_string(), challenge_key),
+        )
+    }
+
     pub fn create_account_with_random_key_signed(
         &mut self,
         algorithm: CryptoAlgorithm,
@@ -181,18 +275,71 @@ impl TransactionBuilder {
     ) -> UncommittedTransaction {
         // Simulate some external service signing account creation credentials
         let vk = signing_key.verifying_key();
-        let hash = Digest::hash_items(&[id.as_bytes(), service_id.as_bytes(), &vk.to_bytes()]);
+        let hash = ServiceChallenge::credentials_digest(id, service_id, &vk);
         let signature = service_signing_key.sign(&hash.to_bytes());
 
+        self.create_account_with_challenge_input(
+            id,
+            service_id,
+            ServiceChallengeInput::Signed(signature),
+            signing_key,
+        )
+    }
+
+    pub fn create_account_with_challenge_input(
+        &mut self,
+        id: &str,
+        service_id: &str,
+        challenge: ServiceChallengeInput,
+        signing_key: SigningKey,
+    ) -> UncommittedTransaction {
+        self.create_account_with_delegation(id, service_id, challenge, signing_key, false)
+    }
+
+    /// Creates an account that allows its service to add and revoke its keys.
+    pub fn create_delegating_account_signed(
+        &mut self,
+        id: &str,
+        service_id: &str,
+        signing_key: SigningKey,
+    ) -> UncommittedTransaction {
+        let Some(service_signing_key) = self.service_keys.get(service_id).cloned() else {
+            panic!("No existing service found for {}", service_id)
+        };
+
+        let hash =
+            ServiceChallenge::credentials_digest(id, service_id, &signing_key.verifying_key());
+        let signature = service_signing_key.sign(&hash.to_bytes());
+
+        self.create_account_with_delegation(
+            id,
+            service_id,
+            ServiceChallengeInput::Signed(signature),
+            signing_key,
+            true,
+        )
+    }
+
+    fn create_account_with_delegation(
+        &mut self,
+        id: &str,
+        service_id: &str,
+        challenge: ServiceChallengeInput,
+        signing_key: SigningKey,
+        delegate_to_service: bool,
+    ) -> UncommittedTransaction {
         let op = Operation::CreateAccount {
             id: id.to_string(),
             service_id: service_id.to_string(),
-            challenge: ServiceChallengeInput::Signed(signature.clone()),
-            key: vk.clone(),
+            challenge,
+            key: signing_key.verifying_key(),
+            valid_until: None,
+            policy: AccountPolicy::default(),
+            delegate_to_service,
         };
 
         let account = Account::default();
-        let transaction = account.prepare_transaction(id.to_string(), op, &signing_key).unwrap();
+        let transaction = self.prepare_transaction(&account, id, vec![op], &signing_key);
 
         UncommittedTransaction {
             transaction,
@@ -243,11 +390,25 @@ impl TransactionBuilder {
         id: &str,
         key: VerifyingKey,
         signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        self.add_key_valid_until(id, key, None, signing_key)
+    }
+
+    pub fn add_key_valid_until(
+        &mut self,
+        id: &str,
+        key: VerifyingKey,
+        valid_until: Option<u64>,
+        signing_key: &SigningKey,
     ) -> UncommittedTransaction {
         let account = self.accounts.get(id).cloned().unwrap_or_default();
-        let op = Operation::AddKey { key: key.clone() };
+        let op = Operation::AddKey {
+            key: key.clone(),
+            valid_until,
+            role: KeyRole::Admin,
+        };
 
-        let transaction = account.prepare_transaction(id.to_string(), op, signing_key).unwrap();
+        let transaction = self.prepare_transaction(&account, id, vec![op], signing_key);
 
         UncommittedTransaction {
             transaction,
@@ -256,6 +417,99 @@ impl TransactionBuilder {
         }
     }
 
+    /// Adds a key that may only authorize the operations permitted by its
+    /// role.
+    pub fn add_key_with_role(
+        &mut self,
+        id: &str,
+        key: VerifyingKey,
+        role: KeyRole,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let account = self.accounts.get(id).cloned().unwrap_or_default();
+        let op = Operation::AddKey {
+            key: key.clone(),
+            valid_until: None,
+            role,
+        };
+
+        let transaction = self.prepare_transaction(&account, id, vec![op], signing_key);
+
+        UncommittedTransaction {
+            transaction,
+            builder: self,
+            post_commit_action: PostCommitAction::UpdateStorageOnly,
+        }
+    }
+
+    /// Adds a key to an account on its behalf, signed by a key of the service
+    /// the account has delegated to.
+    pub fn delegated_add_key(
+        &mut self,
+        id: &str,
+        signing_key: SigningKey,
+        service_signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let account = self.accounts.get(id).cloned().unwrap_or_default();
+        let op = Operation::AddKey {
+            key: signing_key.verifying_key(),
+            valid_until: None,
+            role: KeyRole::Admin,
+        };
+
+        let transaction =
+            self.prepare_transaction_as(&account, id, vec![op], service_signing_key, true);
+
+        UncommittedTransaction {
+            transaction,
+            builder: self,
+            post_commit_action: PostCommitAction::RememberAccountKey(id.to_string(), signing_key),
+        }
+    }
+
+    /// Revokes a key of an account on its behalf, signed by a key of the
+    /// service the account has delegated to.
+    pub fn delegated_revoke_key(
+        &mut self,
+        id: &str,
+        key: VerifyingKey,
+        service_signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let account = self.accounts.get(id).cloned().unwrap_or_default();
+        let op = Operation::RevokeKey { key: key.clone() };
+
+        let transaction =
+            self.prepare_transaction_as(&account, id, vec![op], service_signing_key, true);
+
+        UncommittedTransaction {
+            transaction,
+            builder: self,
+            post_commit_action: PostCommitAction::RemoveAccountKey(id.to_string(), key),
+        }
+    }
+
+    pub fn revoke_delegation_verified_with_root(&mut self, id: &str) -> UncommittedTransaction {
+        let Some(account_signing_keys) = self.account_keys.get(id).cloned() else {
+            panic!("No existing account key for {}", id)
+        };
+
+        let account_signing_key = account_signing_keys.first().unwrap();
+        self.revoke_delegation(id, account_signing_key)
+    }
+
+    pub fn revoke_delegation(
+        &mut self,
+        id: &str,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        self.account_operation(
+            id,
+            Operation::RevokeDelegation,
+            signing_key,
+            PostCommitAction::UpdateStorageOnly,
+        )
+    }
+
     pub fn revoke_key_verified_with_root(
         &mut self,
         id: &str,
@@ -278,7 +532,7 @@ impl TransactionBuilder {
         let account = self.accounts.get(id).cloned().unwrap_or_default();
         let op = Operation::RevokeKey { key: key.clone() };
 
-        let transaction = account.prepare_transaction(id.to_string(), op, signing_key).unwrap();
+        let transaction = self.prepare_transaction(&account, id, vec![op], signing_key);
 
         UncommittedTransaction {
             transaction,
@@ -287,6 +541,262 @@ impl TransactionBuilder {
         }
     }
 
+    pub fn add_encryption_key_verified_with_root(
+        &mut self,
+        id: &str,
+        key: EncryptionKey,
+    ) -> UncommittedTransaction {
+        let Some(account_signing_keys) = self.account_keys.get(id).cloned() else {
+            panic!("No existing account key for {}", id)
+        };
+
+        let account_signing_key = account_signing_keys.first().unwrap();
+        self.add_encryption_key(id, key, account_signing_key)
+    }
+
+    pub fn add_encryption_key(
+        &mut self,
+        id: &str,
+        key: EncryptionKey,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        self.account_operation(
+            id,
+            Operation::AddEncryptionKey { key },
+            signing_key,
+            PostCommitAction::UpdateStorageOnly,
+        )
+    }
+
+    pub fn revoke_encryption_key_verified_with_root(
+        &mut self,
+        id: &str,
+        key: EncryptionKey,
+    ) -> UncommittedTransaction {
+        let Some(account_signing_keys) = self.account_keys.get(id).cloned() else {
+            panic!("No existing account key for {}", id)
+        };
+
+        let account_signing_key = account_signing_keys.first().unwrap();
+        self.revoke_encryption_key(id, key, account_signing_key)
+    }
+
+    pub fn revoke_encryption_key(
+        &mut self,
+        id: &str,
+        key: EncryptionKey,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        self.account_operation(
+            id,
+            Operation::RevokeEncryptionKey { key },
+            signing_key,
+            PostCommitAction::UpdateStorageOnly,
+        )
+    }
+
+    pub fn set_policy_verified_with_root(
+        &mut self,
+        id: &str,
+        policy: AccountPolicy,
+    ) -> UncommittedTransaction {
+        let Some(account_signing_keys) = self.account_keys.get(id).cloned() else {
+            panic!("No existing account key for {}", id)
+        };
+
+        let account_signing_key = account_signing_keys.first().unwrap();
+        self.set_policy(id, policy, account_signing_key)
+    }
+
+    pub fn set_policy(
+        &mut self,
+        id: &str,
+        policy: AccountPolicy,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let account = self.accounts.get(id).cloned().unwrap_or_default();
+        let op = Operation::SetPolicy { policy };
+
+        let transaction = self.prepare_transaction(&account, id, vec![op], signing_key);
+
+        UncommittedTransaction {
+            transaction,
+            builder: self,
+            post_commit_action: PostCommitAction::UpdateStorageOnly,
+        }
+    }
+
+    pub fn set_recovery_verified_with_root(
+        &mut self,
+        id: &str,
+        recovery: Option<RecoveryConfig>,
+    ) -> UncommittedTransaction {
+        let Some(account_signing_keys) = self.account_keys.get(id).cloned() else {
+            panic!("No existing account key for {}", id)
+        };
+
+        let account_signing_key = account_signing_keys.first().unwrap();
+        self.set_recovery(id, recovery, account_signing_key)
+    }
+
+    pub fn set_recovery(
+        &mut self,
+        id: &str,
+        recovery: Option<RecoveryConfig>,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let op = Operation::SetRecovery { recovery };
+        self.account_operation(id, op, signing_key, PostCommitAction::UpdateStorageOnly)
+    }
+
+    pub fn initiate_recovery(
+        &mut self,
+        id: &str,
+        key: VerifyingKey,
+        recovery_signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let op = Operation::InitiateRecovery { key };
+        self.account_operation(
+            id,
+            op,
+            recovery_signing_key,
+            PostCommitAction::UpdateStorageOnly,
+        )
+    }
+
+    pub fn cancel_recovery_verified_with_root(&mut self, id: &str) -> UncommittedTransaction {
+        let Some(account_signing_keys) = self.account_keys.get(id).cloned() else {
+            panic!("No existing account key for {}", id)
+        };
+
+        let account_signing_key = account_signing_keys.first().unwrap();
+        self.cancel_recovery(id, account_signing_key)
+    }
+
+    pub fn cancel_recovery(
+        &mut self,
+        id: &str,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let op = Operation::CancelRecovery;
+        self.account_operation(id, op, signing_key, PostCommitAction::UpdateStorageOnly)
+    }
+
+    /// Completes a pending recovery. As the builder does not know the signing
+    /// key of the recovered account, it forgets all keys of the account.
+    pub fn complete_recovery(
+        &mut self,
+        id: &str,
+        recovery_signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let op = Operation::CompleteRecovery;
+        self.account_operation(
+            id,
+            op,
+            recovery_signing_key,
+            PostCommitAction::ForgetAccountKeys(id.to_string()),
+        )
+    }
+
+    /// Creates a transaction applying all given operations atomically.
+    pub fn operations(
+        &mut self,
+        id: &str,
+        operations: Vec<Operation>,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        self.account_operations(
+            id,
+            operations,
+            signing_key,
+            PostCommitAction::UpdateStorageOnly,
+        )
+    }
+
+    fn account_operation(
+        &mut self,
+        id: &str,
+        operation: Operation,
+        signing_key: &SigningKey,
+        post_commit_action: PostCommitAction,
+    ) -> UncommittedTransaction {
+        self.account_operations(id, vec![operation], signing_key, post_commit_action)
+    }
+
+    fn account_operations(
+        &mut self,
+        id: &str,
+        operations: Vec<Operation>,
+        signing_key: &SigningKey,
+        post_commit_action: PostCommitAction,
+    ) -> UncommittedTransaction {
+        let account = self.accounts.get(id).cloned().unwrap_or_default();
+        let transaction = self.prepare_transaction(&account, id, operations, signing_key);
+
+        UncommittedTransaction {
+            transaction,
+            builder: self,
+            post_commit_action,
+        }
+    }
+
+    fn prepare_transaction(
+        &self,
+        account: &Account,
+        id: &str,
+        operations: Vec<Operation>,
+        signing_key: &SigningKey,
+    ) -> Transaction {
+        self.prepare_transaction_as(account, id, operations, signing_key, false)
+    }
+
+    fn prepare_transaction_as(
+        &self,
+        account: &Account,
+        id: &str,
+        operations: Vec<Operation>,
+        signing_key: &SigningKey,
+        delegated: bool,
+    ) -> Transaction {
+        let mut transaction = Transaction {
+            id: id.to_string(),
+            operations,
+            nonce: account.nonce(),
+            signature: Signature::Placeholder,
+            vk: signing_key.verifying_key(),
+            cosignatures: Vec::new(),
+            delegated,
+            valid_until: self.transaction_valid_until,
+        };
+        transaction
+            .sign(signing_key, &self.context.params.chain_id)
+            .expect("Signing transaction should work");
+        transaction
+    }
+
+    pub fn deactivate_account_verified_with_root(&mut self, id: &str) -> UncommittedTransaction {
+        let Some(account_signing_keys) = self.account_keys.get(id).cloned() else {
+            panic!("No existing account key for {}", id)
+        };
+
+        let account_signing_key = account_signing_keys.first().unwrap();
+        self.deactivate_account(id, account_signing_key)
+    }
+
+    pub fn deactivate_account(
+        &mut self,
+        id: &str,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let op = Operation::DeactivateAccount;
+        self.account_operation(
+            id,
+            op,
+            signing_key,
+            PostCommitAction::ForgetAccountKeys(id.to_string()),
+        )
+    }
+
     pub fn add_randomly_signed_data(
         &mut self,
         algorithm: CryptoAlgorithm,
@@ -414,7 +924,7 @@ impl TransactionBuilder {
             data_signature,
         };
 
-        let transaction = account.prepare_transaction(id.to_string(), op, signing_key).unwrap();
+        let transaction = self.prepare_transaction(&account, id, vec![op], signing_key);
 
         UncommittedTransaction {
             transaction,
@@ -518,7 +1028,7 @@ impl TransactionBuilder {
             data_signature,
         };
 
-        let transaction = account.prepare_transaction(id.to_string(), op, signing_key).unwrap();
+        let transaction = self.prepare_transaction(&account, id, vec![op], signing_key);
 
         UncommittedTransaction {
             transaction,
@@ -526,4 +1036,43 @@ impl TransactionBuilder {
             post_commit_action: PostCommitAction::UpdateStorageOnly,
         }
     }
+
+    pub fn remove_data_by_index_verified_with_root(
+        &mut self,
+        id: &str,
+        index: u64,
+    ) -> UncommittedTransaction {
+        self.remove_data_verified_with_root(id, DataSelector::Index(index))
+    }
+
+    pub fn remove_data_by_digest_verified_with_root(
+        &mut self,
+        id: &str,
+        digest: Digest,
+    ) -> UncommittedTransaction {
+        self.remove_data_verified_with_root(id, DataSelector::Digest(digest))
+    }
+
+    pub fn remove_data_verified_with_root(
+        &mut self,
+        id: &str,
+        selector: DataSelector,
+    ) -> UncommittedTransaction {
+        let Some(account_signing_keys) = self.account_keys.get(id).cloned() else {
+            panic!("No existing account key for {}", id)
+        };
+
+        let account_signing_key = account_signing_keys.first().unwrap();
+        self.remove_data(id, selector, account_signing_key)
+    }
+
+    pub fn remove_data(
+        &mut self,
+        id: &str,
+        selector: DataSelector,
+        signing_key: &SigningKey,
+    ) -> UncommittedTransaction {
+        let op = Operation::RemoveData { selector };
+        self.account_operation(id, op, signing_key, PostCommitAction::UpdateStorageOnly)
+    }
 }
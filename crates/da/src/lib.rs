@@ -1,7 +1,7 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use prism_common::{digest::Digest, transaction::Transaction};
-use prism_keys::{Signature, SigningKey, VerifyingKey};
+use prism_keys::{CryptoAlgorithm, PopVerifiedKey, Signature, Signer, VerifyingKey};
 use prism_serde::{
     binary::ToBinary,
     hex::{FromHex, ToHex},
@@ -25,13 +25,64 @@ pub struct FinalizedEpoch {
 }
 
 impl FinalizedEpoch {
-    pub fn insert_signature(&mut self, key: &SigningKey) {
-        let plaintext = self.encode_to_bytes().unwrap();
-        let signature = key.sign(&plaintext);
+    /// Signs the epoch for the given chain, so that it cannot be replayed on
+    /// other networks.
+    pub fn insert_signature<S: Signer + ?Sized>(
+        &mut self,
+        signer: &S,
+        chain_id: &str,
+    ) -> Result<()> {
+        let plaintext = self.signing_message(chain_id)?;
+        let signature = signer.sign(&plaintext)?;
         self.signature = Some(signature.to_bytes().to_hex());
+        Ok(())
+    }
+
+    /// Adds the BLS12-381 signature of a witness co-signing the epoch to the
+    /// existing signature, so that all signatures are posted as one aggregate
+    /// signature of the same size. See [`FinalizedEpoch::verify_aggregate_signature`].
+    pub fn insert_cosignature<S: Signer + ?Sized>(
+        &mut self,
+        signer: &S,
+        chain_id: &str,
+    ) -> Result<()> {
+        if signer.algorithm() != CryptoAlgorithm::Bls12381 {
+            anyhow::bail!("Only BLS12-381 signatures can be aggregated");
+        }
+        let signature = signer.sign(&self.signing_message(chain_id)?)?;
+        let signature = match self.signature {
+            Some(_) => Signature::aggregate(&[
+                self.decode_signature(CryptoAlgorithm::Bls12381)?,
+                signature,
+            ])?,
+            None => signature,
+        };
+        self.signature = Some(signature.to_bytes().to_hex());
+        Ok(())
+    }
+
+    pub fn verify_signature(&self, vk: VerifyingKey, chain_id: &str) -> Result<()> {
+        let message = self.signing_message(chain_id)?;
+        let signature = self.decode_signature(vk.algorithm())?;
+
+        vk.verify_signature(&message, &signature)
+            .map_err(|e| anyhow::anyhow!("Signature verification failed: {}", e))?;
+        Ok(())
+    }
+
+    /// Verifies that the epoch has been signed by all given BLS12-381 keys,
+    /// e.g. by the prover and its witnesses. The keys must have proven
+    /// possession, see [`VerifyingKey::verify_possession`].
+    pub fn verify_aggregate_signature(&self, vks: &[PopVerifiedKey], chain_id: &str) -> Result<()> {
+        let message = self.signing_message(chain_id)?;
+        let signature = self.decode_signature(CryptoAlgorithm::Bls12381)?;
+
+        VerifyingKey::verify_aggregate(vks, &message, &signature)
+            .map_err(|e| anyhow::anyhow!("Aggregate signature verification failed: {}", e))
     }
 
-    pub fn verify_signature(&self, vk: VerifyingKey) -> Result<()> {
+    /// Returns the signed message, the epoch without signature for the chain.
+    fn signing_message(&self, chain_id: &str) -> Result<Vec<u8>> {
         let epoch_without_signature = FinalizedEpoch {
             height: self.height,
             prev_commitment: self.prev_commitment,
@@ -40,23 +91,20 @@ impl FinalizedEpoch {
             signature: None,
         };
 
-        let message = epoch_without_signature
+        (chain_id, epoch_without_signature)
             .encode_to_bytes()
-            .map_err(|e| anyhow::anyhow!("Failed to serialize epoch: {}", e))?;
+            .map_err(|e| anyhow::anyhow!("Failed to serialize epoch: {}", e))
+    }
 
+    fn decode_signature(&self, algorithm: CryptoAlgorithm) -> Result<Signature> {
         let signature =
             self.signature.as_ref().ok_or_else(|| anyhow::anyhow!("No signature present"))?;
 
         let signature_bytes = Vec::<u8>::from_hex(signature)
             .map_err(|e| anyhow::anyhow!("Failed to decode signature: {}", e))?;
 
-        let signature: Signature =
-            Signature::from_algorithm_and_bytes(vk.algorithm(), signature_bytes.as_slice())
-                .map_err(|_| anyhow::anyhow!("Invalid signature length"))?;
-
-        vk.verify_signature(&message, &signature)
-            .map_err(|e| anyhow::anyhow!("Signature verification failed: {}", e))?;
-        Ok(())
+        Signature::from_algorithm_and_bytes(algorithm, signature_bytes.as_slice())
+            .map_err(|_| anyhow::anyhow!("Invalid signature length"))
     }
 }
 
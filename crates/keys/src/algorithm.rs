@@ -11,6 +11,38 @@ pub enum CryptoAlgorithm {
     Secp256k1,
     /// ECDSA signatures using the NIST P-256 curve, also known as prime256v1
     Secp256r1,
+    /// Diffie-Hellman key agreement using Curve25519. Encryption only.
+    X25519,
+    /// Elliptic-curve Diffie-Hellman key agreement using the NIST P-256 curve.
+    /// Encryption only.
+    Secp256r1Ecdh,
+    /// WebAuthn assertions of passkeys, which are ECDSA signatures using the
+    /// NIST P-256 curve over authenticator and client data. Signatures only,
+    /// made with Secp256r1 keys.
+    WebAuthn,
+    /// Recoverable ECDSA signatures using the secp256k1 curve over
+    /// `personal_sign` (EIP-191) messages, as produced by Ethereum wallets.
+    /// Keys are identified by their Ethereum address.
+    Ethereum,
+    /// BLS signatures using the BLS12-381 curve, with public keys on G1 and
+    /// signatures on G2 (minimal public key size). Signatures of several
+    /// keys can be aggregated into a single signature.
+    Bls12381,
+    /// Module-lattice-based signatures (FIPS 204) with the ML-DSA-65
+    /// parameter set, formerly known as Dilithium. Resistant against quantum
+    /// computers.
+    MlDsa65,
+}
+
+impl CryptoAlgorithm {
+    /// Returns true if keys of the algorithm are used for key agreement, so
+    /// that data can be encrypted for them, rather than for signatures.
+    pub fn is_encryption(&self) -> bool {
+        matches!(
+            self,
+            CryptoAlgorithm::X25519 | CryptoAlgorithm::Secp256r1Ecdh
+        )
+    }
 }
 
 impl std::str::FromStr for CryptoAlgorithm {
@@ -21,6 +53,12 @@ impl std::str::FromStr for CryptoAlgorithm {
             "ed25519" => Ok(CryptoAlgorithm::Ed25519),
             "secp256k1" => Ok(CryptoAlgorithm::Secp256k1),
             "secp256r1" => Ok(CryptoAlgorithm::Secp256r1),
+            "x25519" => Ok(CryptoAlgorithm::X25519),
+            "secp256r1ecdh" => Ok(CryptoAlgorithm::Secp256r1Ecdh),
+            "webauthn" => Ok(CryptoAlgorithm::WebAuthn),
+            "ethereum" => Ok(CryptoAlgorithm::Ethereum),
+            "bls12381" => Ok(CryptoAlgorithm::Bls12381),
+            "mldsa65" => Ok(CryptoAlgorithm::MlDsa65),
             _ => Err(()),
         }
     }
@@ -0,0 +1,87 @@
+use anyhow::{anyhow, bail, Result};
+use ed25519_consensus::{batch, VerificationKeyBytes};
+use rand::{rngs::StdRng, SeedableRng};
+use sha2::{Digest as _, Sha256};
+
+use crate::{Signature, VerifyingKey};
+
+/// Collects signatures to verify them at once. Ed25519 signatures are
+/// verified together, which is considerably cheaper than verifying them one
+/// by one. Signatures of other algorithms are verified when they are queued.
+#[derive(Clone, Default)]
+pub struct BatchVerifier {
+    ed25519: Vec<batch::Item>,
+    /// Hash of all queued Ed25519 signatures. The random coefficients of the
+    /// batch equation are derived from it instead of being sampled, so that
+    /// they cannot be chosen by whoever creates the batch, e.g. the prover
+    /// of a zkVM program.
+    transcript: Sha256,
+}
+
+impl BatchVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the signature of the message for verification. Fails if the
+    /// signature is not part of the batch and invalid.
+    pub fn queue(
+        &mut self,
+        vk: &VerifyingKey,
+        message: &[u8],
+        signature: &Signature,
+    ) -> Result<()> {
+        let (VerifyingKey::Ed25519(vk), Signature::Ed25519(signature)) = (vk, signature) else {
+            return vk.verify_signature(message, signature);
+        };
+
+        self.transcript.update(vk.to_bytes());
+        self.transcript.update(signature.to_bytes());
+        self.transcript.update(Sha256::digest(message));
+        self.ed25519.push(batch::Item::from((
+            VerificationKeyBytes::from(*vk),
+            *signature,
+            message,
+        )));
+        Ok(())
+    }
+
+    /// Returns the number of signatures that are verified by
+    /// [`BatchVerifier::verify`].
+    pub fn len(&self) -> usize {
+        self.ed25519.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ed25519.is_empty()
+    }
+
+    /// Verifies all queued signatures. If the batch is invalid, the
+    /// signatures are verified one by one to report the invalid one.
+    pub fn verify(self) -> Result<()> {
+        if self.ed25519.is_empty() {
+            return Ok(());
+        }
+
+        let seed: [u8; 32] = self.transcript.finalize().into();
+        let mut verifier = batch::Verifier::new();
+        for item in self.ed25519.iter().cloned() {
+            verifier.queue(item);
+        }
+        if verifier.verify(StdRng::from_seed(seed)).is_ok() {
+            return Ok(());
+        }
+
+        for (i, item) in self.ed25519.into_iter().enumerate() {
+            item.verify_single()
+                .map_err(|e| anyhow!("Failed to verify signature {} of batch: {}", i, e))?;
+        }
+        bail!("Failed to verify batch of signatures")
+    }
+}
+
+impl std::fmt::Debug for BatchVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("BatchVerifier").field("ed25519", &self.ed25519.len()).finish()
+    }
+}
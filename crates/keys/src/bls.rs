@@ -0,0 +1,205 @@
+use anyhow::{anyhow, bail, Result};
+use bls12_381::{
+    hash_to_curve::{ExpandMsgXmd, HashToCurve},
+    multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt, Scalar,
+};
+use rand::{rngs::OsRng, RngCore};
+
+/// Domain separation tag of signatures, ciphersuite with proofs of possession
+/// (IETF BLS signatures draft, section 4.2.3), as used by Ethereum
+const SIGNATURE_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+/// Domain separation tag of proofs of possession
+const POP_DST: &[u8] = b"BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+const SIGNING_KEY_LENGTH: usize = 32;
+const VERIFYING_KEY_LENGTH: usize = 48;
+const SIGNATURE_LENGTH: usize = 96;
+
+fn hash_to_g2(message: &[u8], dst: &[u8]) -> G2Affine {
+    <G2Projective as HashToCurve<ExpandMsgXmd<sha2_v0_9::Sha256>>>::hash_to_curve(message, dst)
+        .into()
+}
+
+/// Checks that `e(pk_1, H(m_1)) * ... * e(pk_n, H(m_n)) == e(g1, signature)`.
+fn verify_pairings(pairs: &[(G1Affine, G2Affine)], signature: &Bls12381Signature) -> Result<()> {
+    let mut terms: Vec<(G1Affine, G2Prepared)> =
+        pairs.iter().map(|(pk, hash)| (*pk, G2Prepared::from(*hash))).collect();
+    terms.push((-G1Affine::generator(), G2Prepared::from(signature.0)));
+
+    let terms: Vec<(&G1Affine, &G2Prepared)> = terms.iter().map(|(p, q)| (p, q)).collect();
+    if multi_miller_loop(&terms).final_exponentiation() != Gt::identity() {
+        bail!("Failed to verify signature: pairing check failed");
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+/// BLS12-381 secret key, a scalar of the curve.
+pub struct Bls12381SigningKey(Scalar);
+
+impl Bls12381SigningKey {
+    pub fn random() -> Self {
+        loop {
+            let mut bytes = [0u8; 64];
+            OsRng.fill_bytes(&mut bytes);
+            let scalar = Scalar::from_bytes_wide(&bytes);
+            if scalar != Scalar::zero() {
+                return Bls12381SigningKey(scalar);
+            }
+        }
+    }
+
+    /// Parses a 32-byte big-endian secret key, which must be non-zero and
+    /// smaller than the group order.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut le_bytes: [u8; SIGNING_KEY_LENGTH] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("Invalid BLS12-381 signing key length: {}", bytes.len()))?;
+        le_bytes.reverse();
+        let scalar = Option::<Scalar>::from(Scalar::from_bytes(&le_bytes))
+            .ok_or_else(|| anyhow!("BLS12-381 signing key is not a valid scalar"))?;
+        if scalar == Scalar::zero() {
+            bail!("BLS12-381 signing key is zero");
+        }
+        Ok(Bls12381SigningKey(scalar))
+    }
+
+    /// Returns the 32-byte big-endian encoding of the secret key.
+    pub fn to_bytes(&self) -> [u8; SIGNING_KEY_LENGTH] {
+        let mut bytes = self.0.to_bytes();
+        bytes.reverse();
+        bytes
+    }
+
+    pub fn verifying_key(&self) -> Bls12381VerifyingKey {
+        Bls12381VerifyingKey((G1Projective::generator() * self.0).into())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Bls12381Signature {
+        Bls12381Signature((hash_to_g2(message, SIGNATURE_DST) * self.0).into())
+    }
+
+    /// Signs the own verifying key, proving that the key is not derived from
+    /// the keys of others (a rogue key).
+    pub fn prove_possession(&self) -> Bls12381Signature {
+        let verifying_key = self.verifying_key().to_bytes();
+        Bls12381Signature((hash_to_g2(&verifying_key, POP_DST) * self.0).into())
+    }
+}
+
+impl PartialEq for Bls12381SigningKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Bls12381SigningKey {}
+
+impl std::fmt::Debug for Bls12381SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("Bls12381SigningKey").field(&self.verifying_key()).finish()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// BLS12-381 public key, a point on G1.
+pub struct Bls12381VerifyingKey(G1Affine);
+
+impl Bls12381VerifyingKey {
+    /// Parses a 48-byte compressed point. Points outside of the prime order
+    /// subgroup and the identity are rejected.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: &[u8; VERIFYING_KEY_LENGTH] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("Invalid BLS12-381 verifying key length: {}", bytes.len()))?;
+        let point = Option::<G1Affine>::from(G1Affine::from_compressed(bytes))
+            .ok_or_else(|| anyhow!("Invalid BLS12-381 verifying key"))?;
+        if bool::from(point.is_identity()) {
+            bail!("BLS12-381 verifying key is the identity");
+        }
+        Ok(Bls12381VerifyingKey(point))
+    }
+
+    /// Returns the 48-byte compressed encoding of the point.
+    pub fn to_bytes(&self) -> [u8; VERIFYING_KEY_LENGTH] {
+        self.0.to_compressed()
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &Bls12381Signature) -> Result<()> {
+        verify_pairings(&[(self.0, hash_to_g2(message, SIGNATURE_DST))], signature)
+    }
+
+    pub fn verify_possession(&self, proof: &Bls12381Signature) -> Result<()> {
+        verify_pairings(&[(self.0, hash_to_g2(&self.to_bytes(), POP_DST))], proof)
+            .map_err(|_| anyhow!("Invalid proof of possession"))
+    }
+
+    /// Verifies a signature aggregated from signatures of the same message.
+    /// This is only secure if the possession of all keys has been proven,
+    /// which [`crate::PopVerifiedKey`] guarantees.
+    pub(crate) fn verify_aggregate(
+        keys: &[Bls12381VerifyingKey],
+        message: &[u8],
+        signature: &Bls12381Signature,
+    ) -> Result<()> {
+        if keys.is_empty() {
+            bail!("Cannot verify aggregate signature without keys");
+        }
+        let aggregate = keys.iter().fold(G1Projective::identity(), |acc, key| acc + key.0);
+        verify_pairings(
+            &[(aggregate.into(), hash_to_g2(message, SIGNATURE_DST))],
+            signature,
+        )
+    }
+
+    /// Verifies a signature aggregated from signatures of distinct messages.
+    /// Duplicate messages are rejected, which also prevents rogue keys.
+    pub fn verify_aggregate_messages(
+        pairs: &[(Bls12381VerifyingKey, &[u8])],
+        signature: &Bls12381Signature,
+    ) -> Result<()> {
+        if pairs.is_empty() {
+            bail!("Cannot verify aggregate signature without keys");
+        }
+        for (i, (_, message)) in pairs.iter().enumerate() {
+            if pairs[..i].iter().any(|(_, other)| other == message) {
+                bail!("Aggregate signature contains duplicate messages");
+            }
+        }
+        let pairs: Vec<(G1Affine, G2Affine)> = pairs
+            .iter()
+            .map(|(key, message)| (key.0, hash_to_g2(message, SIGNATURE_DST)))
+            .collect();
+        verify_pairings(&pairs, signature)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// BLS12-381 signature, a point on G2.
+pub struct Bls12381Signature(G2Affine);
+
+impl Bls12381Signature {
+    /// Parses a 96-byte compressed point. Points outside of the prime order
+    /// subgroup are rejected.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: &[u8; SIGNATURE_LENGTH] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("Invalid BLS12-381 signature length: {}", bytes.len()))?;
+        Option::<G2Affine>::from(G2Affine::from_compressed(bytes))
+            .map(Bls12381Signature)
+            .ok_or_else(|| anyhow!("Invalid BLS12-381 signature"))
+    }
+
+    /// Returns the 96-byte compressed encoding of the point.
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_LENGTH] {
+        self.0.to_compressed()
+    }
+
+    /// Aggregates signatures into a single signature of the same size.
+    pub fn aggregate<'a>(signatures: impl IntoIterator<Item = &'a Bls12381Signature>) -> Self {
+        let aggregate = signatures
+            .into_iter()
+            .fold(G2Projective::identity(), |acc, signature| acc + signature.0);
+        Bls12381Signature(aggregate.into())
+    }
+}
@@ -0,0 +1,231 @@
+use anyhow::{anyhow, bail, Result};
+use pkcs8::{
+    der::{
+        asn1::{AnyRef, BitStringRef, OctetStringRef},
+        pem::{self, LineEnding},
+        Decode, Encode,
+    },
+    spki::{AlgorithmIdentifierRef, SubjectPublicKeyInfoRef},
+    EncryptedPrivateKeyInfo, ObjectIdentifier, PrivateKeyInfo,
+};
+use rand::rngs::OsRng;
+use sec1::EcPrivateKey;
+
+use crate::CryptoAlgorithm;
+
+/// RFC 8410
+const ED25519_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+const X25519_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.110");
+/// RFC 5480
+const EC_PUBLIC_KEY_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const SECP256K1_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.10");
+const SECP256R1_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+
+pub(crate) const PUBLIC_KEY_PEM_LABEL: &str = "PUBLIC KEY";
+pub(crate) const PRIVATE_KEY_PEM_LABEL: &str = "PRIVATE KEY";
+pub(crate) const ENCRYPTED_PRIVATE_KEY_PEM_LABEL: &str = "ENCRYPTED PRIVATE KEY";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The curve of an encoded key, as identified by the OIDs of its algorithm.
+/// Encodings don't distinguish between signing and key agreement on the same
+/// curve, so the caller decides which [`CryptoAlgorithm`] the key is used for.
+pub(crate) enum KeyCurve {
+    Ed25519,
+    X25519,
+    Secp256k1,
+    Secp256r1,
+}
+
+impl KeyCurve {
+    pub(crate) fn from_algorithm(algorithm: CryptoAlgorithm) -> Result<Self> {
+        match algorithm {
+            CryptoAlgorithm::Ed25519 => Ok(KeyCurve::Ed25519),
+            CryptoAlgorithm::Secp256k1 => Ok(KeyCurve::Secp256k1),
+            CryptoAlgorithm::Secp256r1
+            | CryptoAlgorithm::Secp256r1Ecdh
+            | CryptoAlgorithm::WebAuthn => Ok(KeyCurve::Secp256r1),
+            CryptoAlgorithm::X25519 => Ok(KeyCurve::X25519),
+            CryptoAlgorithm::Ethereum => {
+                bail!("Ethereum keys are identified by address and cannot be encoded")
+            }
+            CryptoAlgorithm::Bls12381 => bail!("BLS12-381 keys have no standardized encoding"),
+            CryptoAlgorithm::MlDsa65 => bail!("ML-DSA keys are not supported by DER encodings"),
+        }
+    }
+
+    /// Returns the signature algorithm for keys on this curve.
+    pub(crate) fn signature_algorithm(&self) -> Result<CryptoAlgorithm> {
+        match self {
+            KeyCurve::Ed25519 => Ok(CryptoAlgorithm::Ed25519),
+            KeyCurve::Secp256k1 => Ok(CryptoAlgorithm::Secp256k1),
+            KeyCurve::Secp256r1 => Ok(CryptoAlgorithm::Secp256r1),
+            KeyCurve::X25519 => bail!("X25519 keys cannot be used for signatures"),
+        }
+    }
+
+    /// Returns the key agreement algorithm for keys on this curve.
+    pub(crate) fn encryption_algorithm(&self) -> Result<CryptoAlgorithm> {
+        match self {
+            KeyCurve::X25519 => Ok(CryptoAlgorithm::X25519),
+            KeyCurve::Secp256r1 => Ok(CryptoAlgorithm::Secp256r1Ecdh),
+            KeyCurve::Ed25519 | KeyCurve::Secp256k1 => {
+                bail!("{:?} keys cannot be used for encryption", self)
+            }
+        }
+    }
+
+    fn algorithm_oid(&self) -> ObjectIdentifier {
+        match self {
+            KeyCurve::Ed25519 => ED25519_OID,
+            KeyCurve::X25519 => X25519_OID,
+            KeyCurve::Secp256k1 | KeyCurve::Secp256r1 => EC_PUBLIC_KEY_OID,
+        }
+    }
+
+    fn curve_oid(&self) -> Option<ObjectIdentifier> {
+        match self {
+            KeyCurve::Ed25519 | KeyCurve::X25519 => None,
+            KeyCurve::Secp256k1 => Some(SECP256K1_OID),
+            KeyCurve::Secp256r1 => Some(SECP256R1_OID),
+        }
+    }
+
+    fn from_algorithm_identifier(algorithm: &AlgorithmIdentifierRef) -> Result<Self> {
+        if algorithm.oid == ED25519_OID {
+            Ok(KeyCurve::Ed25519)
+        } else if algorithm.oid == X25519_OID {
+            Ok(KeyCurve::X25519)
+        } else if algorithm.oid == EC_PUBLIC_KEY_OID {
+            let curve_oid = algorithm.parameters_oid()?;
+            if curve_oid == SECP256K1_OID {
+                Ok(KeyCurve::Secp256k1)
+            } else if curve_oid == SECP256R1_OID {
+                Ok(KeyCurve::Secp256r1)
+            } else {
+                bail!("Unsupported elliptic curve {}", curve_oid)
+            }
+        } else {
+            bail!("Unsupported key algorithm {}", algorithm.oid)
+        }
+    }
+
+    fn is_edwards(&self) -> bool {
+        matches!(self, KeyCurve::Ed25519 | KeyCurve::X25519)
+    }
+}
+
+/// Encodes a public key as DER SubjectPublicKeyInfo (RFC 5280). Keys on
+/// Weierstrass curves are expected in SEC1 encoding.
+pub(crate) fn encode_spki(curve: KeyCurve, public_key: &[u8]) -> Result<Vec<u8>> {
+    let curve_oid = curve.curve_oid();
+    let spki = SubjectPublicKeyInfoRef {
+        algorithm: AlgorithmIdentifierRef {
+            oid: curve.algorithm_oid(),
+            parameters: curve_oid.as_ref().map(AnyRef::from),
+        },
+        subject_public_key: BitStringRef::from_bytes(public_key)?,
+    };
+    Ok(spki.to_der()?)
+}
+
+/// Decodes a DER SubjectPublicKeyInfo, returning the curve and the raw
+/// public key.
+pub(crate) fn decode_spki(der: &[u8]) -> Result<(KeyCurve, Vec<u8>)> {
+    let spki = SubjectPublicKeyInfoRef::from_der(der)?;
+    let curve = KeyCurve::from_algorithm_identifier(&spki.algorithm)?;
+    let public_key = spki
+        .subject_public_key
+        .as_bytes()
+        .ok_or_else(|| anyhow!("Public key is not byte aligned"))?;
+    Ok((curve, public_key.to_vec()))
+}
+
+/// Encodes a private key as DER PKCS#8 (RFC 5208). Keys on Weierstrass curves
+/// are wrapped in a SEC1 ECPrivateKey, which includes their public key.
+pub(crate) fn encode_pkcs8(
+    curve: KeyCurve,
+    private_key: &[u8],
+    public_key: &[u8],
+) -> Result<Vec<u8>> {
+    let inner = encode_inner_private_key(curve, private_key, public_key)?;
+    let curve_oid = curve.curve_oid();
+    let algorithm = AlgorithmIdentifierRef {
+        oid: curve.algorithm_oid(),
+        parameters: curve_oid.as_ref().map(AnyRef::from),
+    };
+    Ok(PrivateKeyInfo::new(algorithm, &inner).to_der()?)
+}
+
+/// Encodes a private key as DER PKCS#8 EncryptedPrivateKeyInfo (RFC 5958),
+/// encrypted with a key derived from the password using scrypt.
+pub(crate) fn encode_encrypted_pkcs8(
+    curve: KeyCurve,
+    private_key: &[u8],
+    public_key: &[u8],
+    password: &[u8],
+) -> Result<Vec<u8>> {
+    let der = encode_pkcs8(curve, private_key, public_key)?;
+    let document = PrivateKeyInfo::from_der(&der)?.encrypt(OsRng, password)?;
+    Ok(document.as_bytes().to_vec())
+}
+
+/// Decodes a DER PKCS#8 PrivateKeyInfo, returning the curve and the raw
+/// private key.
+pub(crate) fn decode_pkcs8(der: &[u8]) -> Result<(KeyCurve, Vec<u8>)> {
+    let private_key_info = PrivateKeyInfo::from_der(der)?;
+    let curve = KeyCurve::from_algorithm_identifier(&private_key_info.algorithm)?;
+
+    let private_key = if curve.is_edwards() {
+        OctetStringRef::from_der(private_key_info.private_key)?.as_bytes().to_vec()
+    } else {
+        EcPrivateKey::from_der(private_key_info.private_key)?.private_key.to_vec()
+    };
+    Ok((curve, private_key))
+}
+
+/// Decrypts and decodes a DER PKCS#8 EncryptedPrivateKeyInfo. See
+/// [`decode_pkcs8`].
+pub(crate) fn decode_encrypted_pkcs8(der: &[u8], password: &[u8]) -> Result<(KeyCurve, Vec<u8>)> {
+    let document = EncryptedPrivateKeyInfo::from_der(der)?
+        .decrypt(password)
+        .map_err(|e| anyhow!("Failed to decrypt private key: {}", e))?;
+    decode_pkcs8(document.as_bytes())
+}
+
+fn encode_inner_private_key(
+    curve: KeyCurve,
+    private_key: &[u8],
+    public_key: &[u8],
+) -> Result<Vec<u8>> {
+    let inner = if curve.is_edwards() {
+        OctetStringRef::new(private_key)?.to_der()?
+    } else {
+        EcPrivateKey {
+            private_key,
+            parameters: None,
+            public_key: Some(public_key),
+        }
+        .to_der()?
+    };
+    Ok(inner)
+}
+
+pub(crate) fn encode_pem(label: &str, der: &[u8]) -> Result<String> {
+    pem::encode_string(label, LineEnding::LF, der).map_err(|e| anyhow!("Invalid PEM: {}", e))
+}
+
+/// Decodes a PEM document, returning its label and DER contents.
+pub(crate) fn decode_pem(pem: &str) -> Result<(String, Vec<u8>)> {
+    let (label, der) =
+        pem::decode_vec(pem.trim().as_bytes()).map_err(|e| anyhow!("Invalid PEM: {}", e))?;
+    Ok((label.to_string(), der))
+}
+
+/// Decodes a PEM document, ensuring it has the expected label.
+pub(crate) fn decode_pem_with_label(pem: &str, expected_label: &str) -> Result<Vec<u8>> {
+    let (label, der) = decode_pem(pem)?;
+    if label != expected_label {
+        bail!("Expected PEM label {}, got {}", expected_label, label);
+    }
+    Ok(der)
+}
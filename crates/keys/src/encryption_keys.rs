@@ -0,0 +1,329 @@
+use anyhow::{bail, Result};
+use p256::{
+    elliptic_curve::{point::AffineCoordinates, sec1::ToEncodedPoint},
+    PublicKey as Secp256r1PublicKey, SecretKey as Secp256r1SecretKey,
+};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    hash::{Hash, Hasher},
+};
+use utoipa::{
+    openapi::{RefOr, Schema},
+    PartialSchema, ToSchema,
+};
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret as X25519SecretKey};
+
+use crate::{
+    encoding::{self, KeyCurve},
+    payload::CryptoPayload,
+    CryptoAlgorithm,
+};
+use prism_serde::base64::ToBase64;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(try_from = "CryptoPayload", into = "CryptoPayload")]
+/// Represents a public key that data can be encrypted for, by agreeing on a
+/// shared secret with its owner. Encryption keys cannot verify signatures.
+pub enum EncryptionKey {
+    /// Signal, WireGuard, age
+    X25519(X25519PublicKey),
+    /// TLS, HPKE, WebCrypto
+    Secp256r1(Secp256r1PublicKey),
+}
+
+impl Hash for EncryptionKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            EncryptionKey::X25519(_) => {
+                state.write_u8(0);
+                self.to_bytes().hash(state);
+            }
+            EncryptionKey::Secp256r1(_) => {
+                state.write_u8(1);
+                self.to_bytes().hash(state);
+            }
+        }
+    }
+}
+
+impl EncryptionKey {
+    /// Returns the byte representation of the public key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            EncryptionKey::X25519(pk) => pk.as_bytes().to_vec(),
+            EncryptionKey::Secp256r1(pk) => pk.to_sec1_bytes().to_vec(),
+        }
+    }
+
+    pub fn from_algorithm_and_bytes(algorithm: CryptoAlgorithm, bytes: &[u8]) -> Result<Self> {
+        match algorithm {
+            CryptoAlgorithm::X25519 => {
+                let Ok(bytes) = <[u8; 32]>::try_from(bytes) else {
+                    bail!("Invalid X25519 key length: {}", bytes.len());
+                };
+                Ok(EncryptionKey::X25519(X25519PublicKey::from(bytes)))
+            }
+            CryptoAlgorithm::Secp256r1Ecdh => Secp256r1PublicKey::from_sec1_bytes(bytes)
+                .map(EncryptionKey::Secp256r1)
+                .map_err(|e| e.into()),
+            CryptoAlgorithm::Ed25519
+            | CryptoAlgorithm::Secp256k1
+            | CryptoAlgorithm::Secp256r1
+            | CryptoAlgorithm::WebAuthn
+            | CryptoAlgorithm::Ethereum
+            | CryptoAlgorithm::Bls12381
+            | CryptoAlgorithm::MlDsa65 => {
+                bail!("{} is not an encryption algorithm", algorithm)
+            }
+        }
+    }
+
+    pub fn algorithm(&self) -> CryptoAlgorithm {
+        match self {
+            EncryptionKey::X25519(_) => CryptoAlgorithm::X25519,
+            EncryptionKey::Secp256r1(_) => CryptoAlgorithm::Secp256r1Ecdh,
+        }
+    }
+
+    /// Returns the DER encoded SubjectPublicKeyInfo of the public key.
+    pub fn to_der(&self) -> Result<Vec<u8>> {
+        let public_key = match self {
+            EncryptionKey::X25519(pk) => pk.as_bytes().to_vec(),
+            EncryptionKey::Secp256r1(pk) => pk.to_encoded_point(false).as_bytes().to_vec(),
+        };
+        encoding::encode_spki(KeyCurve::from_algorithm(self.algorithm())?, &public_key)
+    }
+
+    /// Returns the PEM encoded SubjectPublicKeyInfo of the public key.
+    pub fn to_pem(&self) -> Result<String> {
+        encoding::encode_pem(encoding::PUBLIC_KEY_PEM_LABEL, &self.to_der()?)
+    }
+
+    /// Parses a DER encoded SubjectPublicKeyInfo. The algorithm is detected
+    /// from the OIDs of the encoding. P-256 keys are always parsed as
+    /// encryption keys.
+    pub fn from_der(bytes: &[u8]) -> Result<Self> {
+        let (curve, public_key) = encoding::decode_spki(bytes)?;
+        EncryptionKey::from_algorithm_and_bytes(curve.encryption_algorithm()?, &public_key)
+    }
+
+    /// Parses a PEM encoded SubjectPublicKeyInfo. See [`EncryptionKey::from_der`].
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let der = encoding::decode_pem_with_label(pem, encoding::PUBLIC_KEY_PEM_LABEL)?;
+        EncryptionKey::from_der(&der)
+    }
+}
+
+impl TryFrom<CryptoPayload> for EncryptionKey {
+    type Error = anyhow::Error;
+
+    fn try_from(value: CryptoPayload) -> std::result::Result<Self, Self::Error> {
+        EncryptionKey::from_algorithm_and_bytes(value.algorithm, &value.bytes)
+    }
+}
+
+impl From<EncryptionKey> for CryptoPayload {
+    fn from(encryption_key: EncryptionKey) -> Self {
+        CryptoPayload {
+            algorithm: encryption_key.algorithm(),
+            bytes: encryption_key.to_bytes(),
+        }
+    }
+}
+
+impl From<X25519PublicKey> for EncryptionKey {
+    fn from(pk: X25519PublicKey) -> Self {
+        EncryptionKey::X25519(pk)
+    }
+}
+
+impl From<Secp256r1PublicKey> for EncryptionKey {
+    fn from(pk: Secp256r1PublicKey) -> Self {
+        EncryptionKey::Secp256r1(pk)
+    }
+}
+
+impl std::fmt::Display for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let encoded = self.to_bytes().to_base64();
+        write!(f, "{}", encoded)
+    }
+}
+
+/// Necessary to represent `EncryptionKey` as `CryptoPayload` in the OpenAPI spec.
+/// See the implementation for `VerifyingKey`.
+impl ToSchema for EncryptionKey {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("CryptoPayload")
+    }
+
+    fn schemas(_schemas: &mut Vec<(String, RefOr<Schema>)>) {
+        CryptoPayload::schemas(_schemas);
+    }
+}
+
+impl PartialSchema for EncryptionKey {
+    fn schema() -> RefOr<Schema> {
+        CryptoPayload::schema()
+    }
+}
+
+#[derive(Clone)]
+/// The secret counterpart of an [`EncryptionKey`], used to derive the shared
+/// secret for data encrypted to it.
+pub enum DecryptionKey {
+    X25519(X25519SecretKey),
+    Secp256r1(Secp256r1SecretKey),
+}
+
+impl DecryptionKey {
+    pub fn new_x25519() -> Self {
+        DecryptionKey::X25519(X25519SecretKey::random_from_rng(OsRng))
+    }
+
+    pub fn new_secp256r1() -> Self {
+        DecryptionKey::Secp256r1(Secp256r1SecretKey::random(&mut OsRng))
+    }
+
+    pub fn new_with_algorithm(algorithm: CryptoAlgorithm) -> Result<Self> {
+        match algorithm {
+            CryptoAlgorithm::X25519 => Ok(DecryptionKey::new_x25519()),
+            CryptoAlgorithm::Secp256r1Ecdh => Ok(DecryptionKey::new_secp256r1()),
+            CryptoAlgorithm::Ed25519
+            | CryptoAlgorithm::Secp256k1
+            | CryptoAlgorithm::Secp256r1
+            | CryptoAlgorithm::WebAuthn
+            | CryptoAlgorithm::Ethereum
+            | CryptoAlgorithm::Bls12381
+            | CryptoAlgorithm::MlDsa65 => {
+                bail!("{} is not an encryption algorithm", algorithm)
+            }
+        }
+    }
+
+    pub fn encryption_key(&self) -> EncryptionKey {
+        match self {
+            DecryptionKey::X25519(sk) => EncryptionKey::X25519(X25519PublicKey::from(sk)),
+            DecryptionKey::Secp256r1(sk) => EncryptionKey::Secp256r1(sk.public_key()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            DecryptionKey::X25519(sk) => sk.to_bytes().to_vec(),
+            DecryptionKey::Secp256r1(sk) => sk.to_bytes().to_vec(),
+        }
+    }
+
+    pub fn from_algorithm_and_bytes(algorithm: CryptoAlgorithm, bytes: &[u8]) -> Result<Self> {
+        match algorithm {
+            CryptoAlgorithm::X25519 => {
+                let Ok(bytes) = <[u8; 32]>::try_from(bytes) else {
+                    bail!("Invalid X25519 key length: {}", bytes.len());
+                };
+                Ok(DecryptionKey::X25519(X25519SecretKey::from(bytes)))
+            }
+            CryptoAlgorithm::Secp256r1Ecdh => Secp256r1SecretKey::from_slice(bytes)
+                .map(DecryptionKey::Secp256r1)
+                .map_err(|e| e.into()),
+            CryptoAlgorithm::Ed25519
+            | CryptoAlgorithm::Secp256k1
+            | CryptoAlgorithm::Secp256r1
+            | CryptoAlgorithm::WebAuthn
+            | CryptoAlgorithm::Ethereum
+            | CryptoAlgorithm::Bls12381
+            | CryptoAlgorithm::MlDsa65 => {
+                bail!("{} is not an encryption algorithm", algorithm)
+            }
+        }
+    }
+
+    pub fn algorithm(&self) -> CryptoAlgorithm {
+        match self {
+            DecryptionKey::X25519(_) => CryptoAlgorithm::X25519,
+            DecryptionKey::Secp256r1(_) => CryptoAlgorithm::Secp256r1Ecdh,
+        }
+    }
+
+    /// Returns the DER encoded PKCS#8 PrivateKeyInfo of the key.
+    pub fn to_pkcs8_der(&self) -> Result<Vec<u8>> {
+        let public_key = match self.encryption_key() {
+            EncryptionKey::X25519(pk) => pk.as_bytes().to_vec(),
+            EncryptionKey::Secp256r1(pk) => pk.to_encoded_point(false).as_bytes().to_vec(),
+        };
+        encoding::encode_pkcs8(
+            KeyCurve::from_algorithm(self.algorithm())?,
+            &self.to_bytes(),
+            &public_key,
+        )
+    }
+
+    /// Returns the PEM encoded PKCS#8 PrivateKeyInfo of the key.
+    pub fn to_pkcs8_pem(&self) -> Result<String> {
+        encoding::encode_pem(encoding::PRIVATE_KEY_PEM_LABEL, &self.to_pkcs8_der()?)
+    }
+
+    /// Parses a DER encoded PKCS#8 PrivateKeyInfo. The algorithm is detected
+    /// from the OIDs of the encoding.
+    pub fn from_pkcs8_der(bytes: &[u8]) -> Result<Self> {
+        let (curve, private_key) = encoding::decode_pkcs8(bytes)?;
+        DecryptionKey::from_algorithm_and_bytes(curve.encryption_algorithm()?, &private_key)
+    }
+
+    /// Parses a PEM encoded PKCS#8 PrivateKeyInfo. See
+    /// [`DecryptionKey::from_pkcs8_der`].
+    pub fn from_pkcs8_pem(pem: &str) -> Result<Self> {
+        let der = encoding::decode_pem_with_label(pem, encoding::PRIVATE_KEY_PEM_LABEL)?;
+        DecryptionKey::from_pkcs8_der(&der)
+    }
+
+    /// Derives the secret shared with the owner of `other`. Both parties
+    /// derive the same secret from their own secret and the other's public
+    /// key. The result is raw key material and should be passed through a KDF
+    /// before it is used as an encryption key.
+    pub fn diffie_hellman(&self, other: &EncryptionKey) -> Result<Vec<u8>> {
+        match (self, other) {
+            (DecryptionKey::X25519(sk), EncryptionKey::X25519(pk)) => {
+                let shared_secret = sk.diffie_hellman(pk);
+                if !shared_secret.was_contributory() {
+                    bail!("X25519 key agreement with a low order point");
+                }
+                Ok(shared_secret.as_bytes().to_vec())
+            }
+            (DecryptionKey::Secp256r1(sk), EncryptionKey::Secp256r1(pk)) => {
+                let shared_point = (pk.to_projective() * *sk.to_nonzero_scalar()).to_affine();
+                Ok(shared_point.x().to_vec())
+            }
+            _ => bail!(
+                "Cannot agree on a secret between {} and {} keys",
+                self.algorithm(),
+                other.algorithm()
+            ),
+        }
+    }
+}
+
+impl PartialEq for DecryptionKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.algorithm() == other.algorithm() && self.to_bytes() == other.to_bytes()
+    }
+}
+
+impl TryFrom<CryptoPayload> for DecryptionKey {
+    type Error = anyhow::Error;
+
+    fn try_from(value: CryptoPayload) -> std::result::Result<Self, Self::Error> {
+        DecryptionKey::from_algorithm_and_bytes(value.algorithm, &value.bytes)
+    }
+}
+
+impl From<DecryptionKey> for CryptoPayload {
+    fn from(decryption_key: DecryptionKey) -> Self {
+        CryptoPayload {
+            algorithm: decryption_key.algorithm(),
+            bytes: decryption_key.to_bytes(),
+        }
+    }
+}
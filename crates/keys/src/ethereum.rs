@@ -0,0 +1,139 @@
+use anyhow::{anyhow, bail, Result};
+use prism_serde::hex::{FromHex, ToHex};
+use secp256k1::{
+    ecdsa::{RecoverableSignature, RecoveryId},
+    Message as Secp256k1Message, PublicKey as Secp256k1VerifyingKey,
+    SecretKey as Secp256k1SigningKey, SECP256K1,
+};
+use sha3::{Digest, Keccak256};
+use std::str::FromStr;
+
+/// Prefix of messages signed with `personal_sign` (EIP-191, version 0x45)
+const PERSONAL_MESSAGE_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n";
+/// Offset of the recovery id in the last byte of signatures (yellow paper)
+const RECOVERY_ID_OFFSET: u8 = 27;
+const ADDRESS_LENGTH: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Ethereum account address, the last 20 bytes of the keccak256 hash of an
+/// uncompressed secp256k1 public key.
+pub struct EthereumAddress([u8; ADDRESS_LENGTH]);
+
+impl EthereumAddress {
+    pub fn from_public_key(public_key: &Secp256k1VerifyingKey) -> Self {
+        let hash = Keccak256::digest(&public_key.serialize_uncompressed()[1..]);
+        let mut address = [0u8; ADDRESS_LENGTH];
+        address.copy_from_slice(&hash[hash.len() - ADDRESS_LENGTH..]);
+        EthereumAddress(address)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let address = bytes
+            .try_into()
+            .map_err(|_| anyhow!("Invalid Ethereum address length: {}", bytes.len()))?;
+        Ok(EthereumAddress(address))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the address in its mixed-case checksum encoding (EIP-55).
+    pub fn to_checksum_string(&self) -> String {
+        let hex = self.0.to_hex();
+        let hash = Keccak256::digest(hex.as_bytes());
+        let checksummed: String = hex
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let nibble = (hash[i / 2] >> (4 * (1 - i % 2))) & 0x0f;
+                if nibble >= 8 {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect();
+        format!("0x{}", checksummed)
+    }
+}
+
+impl std::fmt::Display for EthereumAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_checksum_string())
+    }
+}
+
+impl FromStr for EthereumAddress {
+    type Err = anyhow::Error;
+
+    /// Parses a hex encoded address. Mixed-case addresses must have a valid
+    /// EIP-55 checksum.
+    fn from_str(s: &str) -> Result<Self> {
+        let hex = s.strip_prefix("0x").unwrap_or(s);
+        let address = EthereumAddress::from_slice(&Vec::<u8>::from_hex(hex)?)?;
+
+        let is_mixed_case = hex.chars().any(|c| c.is_ascii_lowercase())
+            && hex.chars().any(|c| c.is_ascii_uppercase());
+        if is_mixed_case && address.to_checksum_string()[2..] != *hex {
+            bail!("Invalid Ethereum address checksum");
+        }
+        Ok(address)
+    }
+}
+
+/// Returns the hash that wallets sign for a message with `personal_sign`.
+pub(crate) fn personal_message_hash(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(PERSONAL_MESSAGE_PREFIX);
+    hasher.update(message.len().to_string().as_bytes());
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+pub(crate) fn sign(signing_key: &Secp256k1SigningKey, message: &[u8]) -> RecoverableSignature {
+    let message = Secp256k1Message::from_digest(personal_message_hash(message));
+    SECP256K1.sign_ecdsa_recoverable(&message, signing_key)
+}
+
+/// Recovers the public key that signed the message. Signatures with high S
+/// values are rejected (EIP-2), so that signatures are not malleable.
+pub(crate) fn recover(
+    message: &[u8],
+    signature: &RecoverableSignature,
+) -> Result<Secp256k1VerifyingKey> {
+    let standard = signature.to_standard();
+    let mut normalized = standard;
+    normalized.normalize_s();
+    if normalized != standard {
+        bail!("Ethereum signature has a high S value");
+    }
+
+    let message = Secp256k1Message::from_digest(personal_message_hash(message));
+    SECP256K1
+        .recover_ecdsa(&message, signature)
+        .map_err(|e| anyhow!("Failed to recover public key: {}", e))
+}
+
+/// Returns the 65-byte `r || s || v` encoding of a signature, as produced by
+/// wallets.
+pub(crate) fn signature_to_bytes(signature: &RecoverableSignature) -> Vec<u8> {
+    let (recovery_id, compact) = signature.serialize_compact();
+    let mut bytes = compact.to_vec();
+    bytes.push(recovery_id.to_i32() as u8 + RECOVERY_ID_OFFSET);
+    bytes
+}
+
+/// Parses a 65-byte `r || s || v` signature. `v` may be either the recovery
+/// id or the recovery id offset by 27.
+pub(crate) fn signature_from_bytes(bytes: &[u8]) -> Result<RecoverableSignature> {
+    let Some((&v, compact)) = bytes.split_last() else {
+        bail!("Empty Ethereum signature");
+    };
+    if compact.len() != 64 {
+        bail!("Invalid Ethereum signature length: {}", bytes.len());
+    }
+    let recovery_id =
+        RecoveryId::from_i32(i32::from(v.checked_sub(RECOVERY_ID_OFFSET).unwrap_or(v)))?;
+    Ok(RecoverableSignature::from_compact(compact, recovery_id)?)
+}
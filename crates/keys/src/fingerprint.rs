@@ -0,0 +1,213 @@
+use anyhow::{anyhow, bail, Result};
+use prism_serde::hex::{FromHex, ToHex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256, Sha512};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+use crate::{CryptoAlgorithm, VerifyingKey};
+
+/// Domain separation of fingerprint digests, including the format version
+const FINGERPRINT_DOMAIN: &[u8] = b"prism-key-fingerprint-v1";
+/// Number of digest bytes in the short form of fingerprints
+const SHORT_FINGERPRINT_LENGTH: usize = 10;
+
+/// Version of the safety number format, included in the hashed data
+const SAFETY_NUMBER_VERSION: u16 = 1;
+/// Number of SHA-512 iterations per account, which makes searching for keys
+/// with colliding safety numbers expensive (as in Signal)
+const SAFETY_NUMBER_ITERATIONS: usize = 5200;
+/// Number of five-digit groups per account
+const SAFETY_NUMBER_GROUPS: usize = 6;
+/// Number of digits of a safety number, covering both accounts
+const SAFETY_NUMBER_LENGTH: usize = 2 * SAFETY_NUMBER_GROUPS * 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+/// Stable fingerprint of a verifying key, a SHA-256 digest of the key tagged
+/// with its algorithm. Rendered as `<algorithm>:<hex digest>`.
+pub struct Fingerprint {
+    algorithm: CryptoAlgorithm,
+    digest: [u8; 32],
+}
+
+impl Fingerprint {
+    pub(crate) fn new(verifying_key: &VerifyingKey) -> Self {
+        let algorithm = verifying_key.algorithm();
+        let digest = Sha256::new()
+            .chain_update(FINGERPRINT_DOMAIN)
+            .chain_update(algorithm_tag(algorithm))
+            .chain_update([0])
+            .chain_update(verifying_key.to_bytes())
+            .finalize()
+            .into();
+        Fingerprint { algorithm, digest }
+    }
+
+    pub fn algorithm(&self) -> CryptoAlgorithm {
+        self.algorithm
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.digest
+    }
+
+    /// Returns the first 80 bits of the digest in groups of four hex digits,
+    /// e.g. `7c0a 6ea5 c5bc 8b1a 44c9`, for comparison by reading aloud.
+    pub fn to_short_string(&self) -> String {
+        self.digest[..SHORT_FINGERPRINT_LENGTH]
+            .chunks(2)
+            .map(|group| group.to_hex())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Returns the lowercase name of the algorithm, as accepted by
+/// [`CryptoAlgorithm::from_str`].
+fn algorithm_tag(algorithm: CryptoAlgorithm) -> String {
+    algorithm.to_string().to_lowercase()
+}
+
+impl std::fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            algorithm_tag(self.algorithm),
+            self.digest.to_hex()
+        )
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (algorithm, digest) =
+            s.split_once(':').ok_or_else(|| anyhow!("Fingerprint lacks an algorithm"))?;
+        let algorithm = CryptoAlgorithm::from_str(algorithm)
+            .map_err(|_| anyhow!("Unknown fingerprint algorithm {}", algorithm))?;
+        let digest =
+            <[u8; 32]>::from_hex(digest).map_err(|e| anyhow!("Invalid fingerprint: {}", e))?;
+        Ok(Fingerprint { algorithm, digest })
+    }
+}
+
+impl TryFrom<String> for Fingerprint {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        Fingerprint::from_str(&value)
+    }
+}
+
+impl From<Fingerprint> for String {
+    fn from(fingerprint: Fingerprint) -> Self {
+        fingerprint.to_string()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(try_from = "String", into = "String")]
+#[schema(
+    value_type = String,
+    example = "05871 33208 94311 60263 51876 09723 10958 88232 71124 55093 30487 19260"
+)]
+/// Safety number of two accounts, as popularized by Signal. Both parties
+/// compute the same 60 digits from the ids and keys of both accounts and
+/// compare them out of band, e.g. in person. If any key of either account
+/// changes, the safety number changes.
+pub struct SafetyNumber(String);
+
+impl SafetyNumber {
+    /// Computes the safety number of two accounts from their ids and current
+    /// keys. The order of the accounts and of their keys does not matter.
+    pub fn new(
+        id: &str,
+        keys: &[VerifyingKey],
+        other_id: &str,
+        other_keys: &[VerifyingKey],
+    ) -> Result<Self> {
+        let digits = account_digits(id, keys)?;
+        let other_digits = account_digits(other_id, other_keys)?;
+        if digits <= other_digits {
+            Ok(SafetyNumber(digits + &other_digits))
+        } else {
+            Ok(SafetyNumber(other_digits + &digits))
+        }
+    }
+
+    /// Returns the 60 digits without separators.
+    pub fn digits(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Returns the 30 digits contributed by a single account.
+fn account_digits(id: &str, keys: &[VerifyingKey]) -> Result<String> {
+    if keys.is_empty() {
+        bail!("Account {} has no keys to compute a safety number from", id);
+    }
+    let mut fingerprints: Vec<[u8; 32]> =
+        keys.iter().map(|key| key.fingerprint().to_bytes()).collect();
+    fingerprints.sort();
+    fingerprints.dedup();
+    let key_material = fingerprints.concat();
+
+    let mut hash = Sha512::new()
+        .chain_update(SAFETY_NUMBER_VERSION.to_be_bytes())
+        .chain_update((fingerprints.len() as u32).to_be_bytes())
+        .chain_update(&key_material)
+        .chain_update(id.as_bytes())
+        .finalize();
+    for _ in 1..SAFETY_NUMBER_ITERATIONS {
+        hash = Sha512::new().chain_update(hash).chain_update(&key_material).finalize();
+    }
+
+    Ok(hash[..SAFETY_NUMBER_GROUPS * 5]
+        .chunks(5)
+        .map(|chunk| {
+            let value = chunk.iter().fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte));
+            format!("{:05}", value % 100_000)
+        })
+        .collect())
+}
+
+impl std::fmt::Display for SafetyNumber {
+    /// Formats the digits in twelve groups of five, separated by spaces.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let groups: Vec<&str> = (0..self.0.len()).step_by(5).map(|i| &self.0[i..i + 5]).collect();
+        write!(f, "{}", groups.join(" "))
+    }
+}
+
+impl FromStr for SafetyNumber {
+    type Err = anyhow::Error;
+
+    /// Parses a safety number, ignoring whitespace between digits.
+    fn from_str(s: &str) -> Result<Self> {
+        let digits: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        if digits.len() != SAFETY_NUMBER_LENGTH || !digits.chars().all(|c| c.is_ascii_digit()) {
+            bail!(
+                "Safety number must consist of {} digits",
+                SAFETY_NUMBER_LENGTH
+            );
+        }
+        Ok(SafetyNumber(digits))
+    }
+}
+
+impl TryFrom<String> for SafetyNumber {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        SafetyNumber::from_str(&value)
+    }
+}
+
+impl From<SafetyNumber> for String {
+    fn from(safety_number: SafetyNumber) -> Self {
+        safety_number.to_string()
+    }
+}
@@ -0,0 +1,269 @@
+use anyhow::{anyhow, bail, Result};
+use bip39::Mnemonic;
+use hmac::{Hmac, Mac};
+use p256::{
+    ecdsa::SigningKey as Secp256r1SigningKey,
+    elliptic_curve::{ff::Field, PrimeField},
+    Scalar as Secp256r1Scalar,
+};
+use secp256k1::{Scalar as Secp256k1Scalar, SecretKey as Secp256k1SigningKey, SECP256K1};
+use sha2::Sha512;
+use std::str::FromStr;
+
+use crate::{CryptoAlgorithm, SigningKey};
+
+/// Offset of hardened child indices
+pub const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+/// Keys of the HMAC computing master keys from seeds (SLIP-10)
+const ED25519_SEED_KEY: &[u8] = b"ed25519 seed";
+const SECP256K1_SEED_KEY: &[u8] = b"Bitcoin seed";
+const SECP256R1_SEED_KEY: &[u8] = b"Nist256p1 seed";
+
+/// Number of words of generated mnemonics, 256 bits of entropy
+const MNEMONIC_WORD_COUNT: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Curve {
+    Ed25519,
+    Secp256k1,
+    Secp256r1,
+}
+
+impl Curve {
+    fn from_algorithm(algorithm: CryptoAlgorithm) -> Result<Self> {
+        match algorithm {
+            CryptoAlgorithm::Ed25519 => Ok(Curve::Ed25519),
+            CryptoAlgorithm::Secp256k1 | CryptoAlgorithm::Ethereum => Ok(Curve::Secp256k1),
+            CryptoAlgorithm::Secp256r1 => Ok(Curve::Secp256r1),
+            CryptoAlgorithm::X25519
+            | CryptoAlgorithm::Secp256r1Ecdh
+            | CryptoAlgorithm::WebAuthn
+            | CryptoAlgorithm::Bls12381
+            | CryptoAlgorithm::MlDsa65 => {
+                bail!("{} keys cannot be derived hierarchically", algorithm)
+            }
+        }
+    }
+
+    fn seed_key(&self) -> &'static [u8] {
+        match self {
+            Curve::Ed25519 => ED25519_SEED_KEY,
+            Curve::Secp256k1 => SECP256K1_SEED_KEY,
+            Curve::Secp256r1 => SECP256R1_SEED_KEY,
+        }
+    }
+
+    /// Returns the compressed public key of a private key on a Weierstrass
+    /// curve, which non-hardened derivation is based on.
+    fn public_key(&self, private_key: &[u8; 32]) -> Result<Vec<u8>> {
+        match self {
+            Curve::Ed25519 => bail!("Ed25519 keys only support hardened derivation"),
+            Curve::Secp256k1 => {
+                let sk = Secp256k1SigningKey::from_slice(private_key)?;
+                Ok(sk.public_key(SECP256K1).serialize().to_vec())
+            }
+            Curve::Secp256r1 => {
+                let sk = Secp256r1SigningKey::from_slice(private_key)?;
+                Ok(sk.verifying_key().to_encoded_point(true).as_bytes().to_vec())
+            }
+        }
+    }
+
+    /// Returns `tweak + private_key` modulo the curve order, or `None` if the
+    /// tweak is not smaller than the order or the result is zero. Private
+    /// keys on Ed25519 are replaced by the tweak.
+    fn add_private_keys(&self, tweak: &[u8; 32], private_key: &[u8; 32]) -> Option<[u8; 32]> {
+        match self {
+            Curve::Ed25519 => Some(*tweak),
+            Curve::Secp256k1 => {
+                let tweak = Secp256k1Scalar::from_be_bytes(*tweak).ok()?;
+                let sk = Secp256k1SigningKey::from_slice(private_key).ok()?;
+                sk.add_tweak(&tweak).ok().map(|sk| sk.secret_bytes())
+            }
+            Curve::Secp256r1 => {
+                let tweak =
+                    Option::<Secp256r1Scalar>::from(Secp256r1Scalar::from_repr((*tweak).into()))?;
+                let sk = Secp256r1SigningKey::from_slice(private_key).ok()?;
+                let child = *sk.as_nonzero_scalar().as_ref() + tweak;
+                if bool::from(child.is_zero()) {
+                    return None;
+                }
+                Some(child.to_repr().into())
+            }
+        }
+    }
+
+    /// Returns true if the bytes are a valid private key on the curve.
+    fn is_valid_private_key(&self, private_key: &[u8; 32]) -> bool {
+        match self {
+            Curve::Ed25519 => true,
+            Curve::Secp256k1 => Secp256k1SigningKey::from_slice(private_key).is_ok(),
+            Curve::Secp256r1 => Secp256r1SigningKey::from_slice(private_key).is_ok(),
+        }
+    }
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    let output = mac.finalize().into_bytes();
+    let mut left = [0u8; 32];
+    let mut right = [0u8; 32];
+    left.copy_from_slice(&output[..32]);
+    right.copy_from_slice(&output[32..]);
+    (left, right)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// BIP-32 derivation path, e.g. `m/44'/60'/0'/0/0`. Hardened indices are
+/// marked with `'` or `h`.
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Creates a path from child indices. Hardened indices include
+    /// [`HARDENED_OFFSET`].
+    pub fn new(indices: Vec<u32>) -> Self {
+        DerivationPath(indices)
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut segments = s.split('/');
+        if segments.next() != Some("m") {
+            bail!("Derivation path must start with m");
+        }
+        let indices = segments
+            .map(|segment| {
+                let (index, hardened) = match segment.strip_suffix(['\'', 'h', 'H']) {
+                    Some(index) => (index, true),
+                    None => (segment, false),
+                };
+                let index: u32 = index
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid derivation path index {}", segment))?;
+                if index >= HARDENED_OFFSET {
+                    bail!("Derivation path index {} is too large", segment);
+                }
+                Ok(if hardened {
+                    index + HARDENED_OFFSET
+                } else {
+                    index
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(DerivationPath(indices))
+    }
+}
+
+impl std::fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            if index >= &HARDENED_OFFSET {
+                write!(f, "/{}'", index - HARDENED_OFFSET)?;
+            } else {
+                write!(f, "/{}", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+/// Private key with chain code, from which child keys are derived as
+/// specified by SLIP-10. On secp256k1, this is identical to BIP-32. On
+/// Ed25519, only hardened children can be derived.
+pub struct ExtendedSigningKey {
+    algorithm: CryptoAlgorithm,
+    curve: Curve,
+    private_key: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+impl ExtendedSigningKey {
+    /// Derives the master key of the algorithm from a seed, which should
+    /// be between 16 and 64 bytes long.
+    pub fn from_seed(algorithm: CryptoAlgorithm, seed: &[u8]) -> Result<Self> {
+        let curve = Curve::from_algorithm(algorithm)?;
+        let (mut private_key, mut chain_code) = hmac_sha512(curve.seed_key(), seed);
+        while !curve.is_valid_private_key(&private_key) {
+            (private_key, chain_code) =
+                hmac_sha512(curve.seed_key(), &[private_key, chain_code].concat());
+        }
+        Ok(ExtendedSigningKey {
+            algorithm,
+            curve,
+            private_key,
+            chain_code,
+        })
+    }
+
+    /// Derives the master key of the algorithm from a BIP-39 mnemonic and
+    /// an optional passphrase, which may be empty.
+    pub fn from_mnemonic(
+        algorithm: CryptoAlgorithm,
+        mnemonic: &str,
+        passphrase: &str,
+    ) -> Result<Self> {
+        let mnemonic = Mnemonic::parse(mnemonic).map_err(|e| anyhow!("Invalid mnemonic: {}", e))?;
+        ExtendedSigningKey::from_seed(algorithm, &mnemonic.to_seed(passphrase))
+    }
+
+    pub fn derive_child(&self, index: u32) -> Result<Self> {
+        let hardened = index >= HARDENED_OFFSET;
+        let mut data = if hardened {
+            [&[0u8], self.private_key.as_slice()].concat()
+        } else {
+            self.curve.public_key(&self.private_key)?
+        };
+        data.extend_from_slice(&index.to_be_bytes());
+
+        loop {
+            let (tweak, chain_code) = hmac_sha512(&self.chain_code, &data);
+            if let Some(private_key) = self.curve.add_private_keys(&tweak, &self.private_key) {
+                return Ok(ExtendedSigningKey {
+                    algorithm: self.algorithm,
+                    curve: self.curve,
+                    private_key,
+                    chain_code,
+                });
+            }
+            data = [&[1u8], chain_code.as_slice(), &index.to_be_bytes()].concat();
+        }
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self> {
+        path.indices().iter().try_fold(self.clone(), |key, &index| key.derive_child(index))
+    }
+
+    pub fn chain_code(&self) -> [u8; 32] {
+        self.chain_code
+    }
+
+    pub fn signing_key(&self) -> SigningKey {
+        SigningKey::from_algorithm_and_bytes(self.algorithm, &self.private_key)
+            .expect("Derived private keys are valid")
+    }
+}
+
+impl std::fmt::Debug for ExtendedSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ExtendedSigningKey")
+            .field("algorithm", &self.algorithm)
+            .field("verifying_key", &self.signing_key().verifying_key())
+            .finish()
+    }
+}
+
+/// Generates a random 24-word BIP-39 mnemonic in English.
+pub fn generate_mnemonic() -> String {
+    Mnemonic::generate(MNEMONIC_WORD_COUNT).expect("24 is a valid mnemonic word count").to_string()
+}
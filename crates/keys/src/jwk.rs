@@ -0,0 +1,124 @@
+use anyhow::{anyhow, bail, Result};
+use prism_serde::base64::{FromBase64Url, ToBase64Url};
+use serde::{Deserialize, Serialize};
+
+use crate::CryptoAlgorithm;
+
+const KTY_OKP: &str = "OKP";
+const KTY_EC: &str = "EC";
+const CRV_ED25519: &str = "Ed25519";
+const CRV_SECP256K1: &str = "secp256k1";
+const CRV_P256: &str = "P-256";
+
+/// Length of a coordinate of a point on secp256k1 or secp256r1
+const EC_COORDINATE_LENGTH: usize = 32;
+/// Tag of an uncompressed SEC1 point
+const SEC1_UNCOMPRESSED_TAG: u8 = 0x04;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// JSON Web Key (RFC 7517) of an Ed25519 key (RFC 8037) or an elliptic curve
+/// key on P-256 or secp256k1 (RFC 7518, RFC 8812). Members not needed to
+/// reconstruct the key, like `alg` or `key_ops`, are ignored.
+pub struct Jwk {
+    /// Key type, `OKP` or `EC`
+    pub kty: String,
+    /// Curve of the key
+    pub crv: String,
+    /// Public key for `OKP` keys, x coordinate of the public point for `EC`
+    /// keys
+    pub x: String,
+    /// y coordinate of the public point for `EC` keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+    /// Private key, if this is a private JWK
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub d: Option<String>,
+}
+
+impl Jwk {
+    /// Creates a JWK from a public key and an optional private key. Keys on
+    /// Weierstrass curves are expected as uncompressed SEC1 points.
+    pub(crate) fn new(
+        algorithm: CryptoAlgorithm,
+        public_key: &[u8],
+        private_key: Option<&[u8]>,
+    ) -> Result<Self> {
+        let d = private_key.map(|d| d.to_base64_url());
+        match algorithm {
+            CryptoAlgorithm::Ed25519 => Ok(Jwk {
+                kty: KTY_OKP.to_string(),
+                crv: CRV_ED25519.to_string(),
+                x: public_key.to_base64_url(),
+                y: None,
+                d,
+            }),
+            CryptoAlgorithm::Secp256k1 | CryptoAlgorithm::Secp256r1 => {
+                let Some((&SEC1_UNCOMPRESSED_TAG, coordinates)) = public_key.split_first() else {
+                    bail!("Expected an uncompressed public key");
+                };
+                if coordinates.len() != 2 * EC_COORDINATE_LENGTH {
+                    bail!("Invalid public key length");
+                }
+                let (x, y) = coordinates.split_at(EC_COORDINATE_LENGTH);
+                let crv = if algorithm == CryptoAlgorithm::Secp256k1 {
+                    CRV_SECP256K1
+                } else {
+                    CRV_P256
+                };
+                Ok(Jwk {
+                    kty: KTY_EC.to_string(),
+                    crv: crv.to_string(),
+                    x: x.to_base64_url(),
+                    y: Some(y.to_base64_url()),
+                    d,
+                })
+            }
+            CryptoAlgorithm::X25519
+            | CryptoAlgorithm::Secp256r1Ecdh
+            | CryptoAlgorithm::WebAuthn
+            | CryptoAlgorithm::Ethereum
+            | CryptoAlgorithm::Bls12381
+            | CryptoAlgorithm::MlDsa65 => {
+                bail!("{} keys cannot be encoded as JWK", algorithm)
+            }
+        }
+    }
+
+    /// Returns the signature algorithm of the key, based on `kty` and `crv`.
+    pub fn algorithm(&self) -> Result<CryptoAlgorithm> {
+        match (self.kty.as_str(), self.crv.as_str()) {
+            (KTY_OKP, CRV_ED25519) => Ok(CryptoAlgorithm::Ed25519),
+            (KTY_EC, CRV_SECP256K1) => Ok(CryptoAlgorithm::Secp256k1),
+            (KTY_EC, CRV_P256) => Ok(CryptoAlgorithm::Secp256r1),
+            (kty, crv) => bail!("Unsupported JWK with kty {} and crv {}", kty, crv),
+        }
+    }
+
+    /// Returns the public key. Keys on Weierstrass curves are returned as
+    /// uncompressed SEC1 points.
+    pub(crate) fn public_key_bytes(&self) -> Result<Vec<u8>> {
+        let x = decode_member("x", &self.x)?;
+        if self.algorithm()? == CryptoAlgorithm::Ed25519 {
+            return Ok(x);
+        }
+
+        let y = decode_member(
+            "y",
+            self.y.as_ref().ok_or_else(|| anyhow!("JWK is missing y"))?,
+        )?;
+        if x.len() != EC_COORDINATE_LENGTH || y.len() != EC_COORDINATE_LENGTH {
+            bail!("Invalid JWK coordinate length");
+        }
+        Ok([&[SEC1_UNCOMPRESSED_TAG], x.as_slice(), y.as_slice()].concat())
+    }
+
+    /// Returns the private key, failing for public JWKs.
+    pub(crate) fn private_key_bytes(&self) -> Result<Vec<u8>> {
+        let d = self.d.as_ref().ok_or_else(|| anyhow!("JWK does not contain a private key"))?;
+        decode_member("d", d)
+    }
+}
+
+fn decode_member(name: &str, value: &str) -> Result<Vec<u8>> {
+    Vec::<u8>::from_base64_url(value).map_err(|e| anyhow!("Invalid JWK member {}: {}", name, e))
+}
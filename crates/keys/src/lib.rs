@@ -1,13 +1,35 @@
 mod algorithm;
+mod batch;
+mod bls;
+mod encoding;
+mod encryption_keys;
+mod ethereum;
+mod fingerprint;
+mod hd;
+mod jwk;
+mod mldsa;
+mod openssh;
 mod payload;
 mod signatures;
+mod signer;
 mod signing_keys;
 mod verifying_keys;
+mod webauthn;
 
 pub use algorithm::*;
+pub use batch::*;
+pub use bls::*;
+pub use encryption_keys::*;
+pub use ethereum::EthereumAddress;
+pub use fingerprint::*;
+pub use hd::*;
+pub use jwk::*;
+pub use mldsa::*;
 pub use signatures::*;
+pub use signer::*;
 pub use signing_keys::*;
 pub use verifying_keys::*;
+pub use webauthn::*;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,115 @@
+use anyhow::{anyhow, Result};
+use ml_dsa::{
+    EncodedVerifyingKey, Keypair as _, MlDsa65, Seed, Signature as MlDsaSignature, Signer as _,
+    SigningKey as MlDsaSigningKey, Verifier as _, VerifyingKey as MlDsaVerifyingKey,
+};
+use prism_serde::hex::ToHex;
+use rand::{rngs::OsRng, RngCore};
+
+/// Length of the seed from which signing keys are expanded (FIPS 204)
+const SEED_LENGTH: usize = 32;
+
+#[derive(Clone)]
+/// ML-DSA-65 signing key (FIPS 204, security category 3). The key is
+/// represented by the 32-byte seed it is expanded from.
+pub struct MlDsa65SigningKey(MlDsaSigningKey<MlDsa65>);
+
+impl MlDsa65SigningKey {
+    pub fn random() -> Self {
+        let mut seed = [0u8; SEED_LENGTH];
+        OsRng.fill_bytes(&mut seed);
+        MlDsa65SigningKey(MlDsaSigningKey::from_seed(&Seed::from(seed)))
+    }
+
+    /// Expands a signing key from its 32-byte seed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let seed = Seed::try_from(bytes)
+            .map_err(|_| anyhow!("Invalid ML-DSA-65 seed length: {}", bytes.len()))?;
+        Ok(MlDsa65SigningKey(MlDsaSigningKey::from_seed(&seed)))
+    }
+
+    /// Returns the 32-byte seed of the signing key.
+    pub fn to_bytes(&self) -> [u8; SEED_LENGTH] {
+        self.0.to_seed().into()
+    }
+
+    pub fn verifying_key(&self) -> MlDsa65VerifyingKey {
+        MlDsa65VerifyingKey(self.0.verifying_key())
+    }
+
+    /// Signs the message with the deterministic variant of ML-DSA and an
+    /// empty context.
+    pub fn sign(&self, message: &[u8]) -> MlDsa65Signature {
+        MlDsa65Signature(self.0.sign(message))
+    }
+}
+
+impl PartialEq for MlDsa65SigningKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_seed() == other.0.as_seed()
+    }
+}
+
+impl Eq for MlDsa65SigningKey {}
+
+impl std::fmt::Debug for MlDsa65SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("MlDsa65SigningKey").field(&self.verifying_key()).finish()
+    }
+}
+
+#[derive(Clone, PartialEq)]
+/// ML-DSA-65 verifying key.
+pub struct MlDsa65VerifyingKey(MlDsaVerifyingKey<MlDsa65>);
+
+impl MlDsa65VerifyingKey {
+    /// Parses a 1952-byte encoded verifying key.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let encoded = EncodedVerifyingKey::<MlDsa65>::try_from(bytes)
+            .map_err(|_| anyhow!("Invalid ML-DSA-65 verifying key length: {}", bytes.len()))?;
+        Ok(MlDsa65VerifyingKey(MlDsaVerifyingKey::decode(&encoded)))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.encode().to_vec()
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &MlDsa65Signature) -> Result<()> {
+        self.0
+            .verify(message, &signature.0)
+            .map_err(|e| anyhow!("Failed to verify signature: {}", e))
+    }
+}
+
+impl Eq for MlDsa65VerifyingKey {}
+
+impl std::fmt::Debug for MlDsa65VerifyingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("MlDsa65VerifyingKey").field(&self.to_bytes().to_hex()).finish()
+    }
+}
+
+#[derive(Clone, PartialEq)]
+/// ML-DSA-65 signature.
+pub struct MlDsa65Signature(MlDsaSignature<MlDsa65>);
+
+impl MlDsa65Signature {
+    /// Parses a 3309-byte encoded signature.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        MlDsaSignature::try_from(bytes)
+            .map(MlDsa65Signature)
+            .map_err(|_| anyhow!("Invalid ML-DSA-65 signature"))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.encode().to_vec()
+    }
+}
+
+impl Eq for MlDsa65Signature {}
+
+impl std::fmt::Debug for MlDsa65Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("MlDsa65Signature").field(&self.to_bytes().to_hex()).finish()
+    }
+}
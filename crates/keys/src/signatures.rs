@@ -3,15 +3,19 @@ use std::borrow::Cow;
 use anyhow::{bail, Result};
 use ed25519_consensus::Signature as Ed25519Signature;
 use p256::ecdsa::Signature as Secp256r1Signature;
-use secp256k1::ecdsa::Signature as Secp256k1Signature;
+use secp256k1::ecdsa::{RecoverableSignature, Signature as Secp256k1Signature};
 
+use prism_serde::binary::{FromBinary, ToBinary};
 use serde::{Deserialize, Serialize};
 use utoipa::{
     openapi::{RefOr, Schema},
     PartialSchema, ToSchema,
 };
 
-use crate::{payload::CryptoPayload, CryptoAlgorithm};
+use crate::{
+    ethereum, payload::CryptoPayload, Bls12381Signature, CryptoAlgorithm, MlDsa65Signature,
+    WebAuthnSignature,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
 #[serde(try_from = "CryptoPayload", into = "CryptoPayload")]
@@ -19,6 +23,10 @@ pub enum Signature {
     Secp256k1(Secp256k1Signature),
     Ed25519(Ed25519Signature),
     Secp256r1(Secp256r1Signature),
+    WebAuthn(WebAuthnSignature),
+    Ethereum(RecoverableSignature),
+    Bls12381(Bls12381Signature),
+    MlDsa65(Box<MlDsa65Signature>),
     #[default]
     Placeholder,
 }
@@ -29,15 +37,28 @@ impl Signature {
             Signature::Ed25519(sig) => sig.to_bytes().to_vec(),
             Signature::Secp256k1(sig) => sig.serialize_compact().to_vec(),
             Signature::Secp256r1(sig) => sig.to_vec(),
+            Signature::WebAuthn(sig) => {
+                sig.encode_to_bytes().expect("WebAuthn signatures can be encoded")
+            }
+            Signature::Ethereum(sig) => ethereum::signature_to_bytes(sig),
+            Signature::Bls12381(sig) => sig.to_bytes().to_vec(),
+            Signature::MlDsa65(sig) => sig.to_bytes(),
             Signature::Placeholder => vec![],
         }
     }
 
+    /// Returns the DER encoding of ECDSA signatures. Ed25519 signatures have
+    /// no ASN.1 structure and are embedded in DER documents as their raw
+    /// bytes (RFC 8410), which is what is returned for them.
     pub fn to_der(&self) -> Result<Vec<u8>> {
         let der = match self {
-            Signature::Ed25519(_) => bail!("Ed25519 sig from DER format is not implemented"),
+            Signature::Ed25519(sig) => sig.to_bytes().to_vec(),
             Signature::Secp256k1(sig) => sig.serialize_der().to_vec(),
             Signature::Secp256r1(sig) => sig.to_der().as_bytes().to_vec(),
+            Signature::WebAuthn(_) => bail!("WebAuthn signatures have no DER encoding"),
+            Signature::Ethereum(_) => bail!("Ethereum signatures have no DER encoding"),
+            Signature::Bls12381(_) => bail!("BLS12-381 signatures have no DER encoding"),
+            Signature::MlDsa65(_) => bail!("ML-DSA signatures have no DER encoding"),
             Signature::Placeholder => vec![],
         };
         Ok(der)
@@ -54,18 +75,42 @@ impl Signature {
             CryptoAlgorithm::Secp256r1 => Secp256r1Signature::from_slice(bytes)
                 .map(Signature::Secp256r1)
                 .map_err(|e| e.into()),
+            CryptoAlgorithm::WebAuthn => WebAuthnSignature::decode_from_bytes(bytes)
+                .map(Signature::WebAuthn)
+                .map_err(|e| e.into()),
+            CryptoAlgorithm::Ethereum => {
+                ethereum::signature_from_bytes(bytes).map(Signature::Ethereum)
+            }
+            CryptoAlgorithm::Bls12381 => {
+                Bls12381Signature::from_bytes(bytes).map(Signature::Bls12381)
+            }
+            CryptoAlgorithm::MlDsa65 => {
+                MlDsa65Signature::from_bytes(bytes).map(|sig| Signature::MlDsa65(Box::new(sig)))
+            }
+            CryptoAlgorithm::X25519 | CryptoAlgorithm::Secp256r1Ecdh => {
+                bail!("{} is not a signature algorithm", algorithm)
+            }
         }
     }
 
     pub fn from_algorithm_and_der(algorithm: CryptoAlgorithm, bytes: &[u8]) -> Result<Self> {
         match algorithm {
-            CryptoAlgorithm::Ed25519 => bail!("Ed25519 sig from DER format is not implemented"),
+            CryptoAlgorithm::Ed25519 => {
+                Ed25519Signature::try_from(bytes).map(Signature::Ed25519).map_err(|e| e.into())
+            }
             CryptoAlgorithm::Secp256k1 => {
                 Secp256k1Signature::from_der(bytes).map(Signature::Secp256k1).map_err(|e| e.into())
             }
             CryptoAlgorithm::Secp256r1 => {
                 Secp256r1Signature::from_der(bytes).map(Signature::Secp256r1).map_err(|e| e.into())
             }
+            CryptoAlgorithm::WebAuthn => bail!("WebAuthn signatures have no DER encoding"),
+            CryptoAlgorithm::Ethereum => bail!("Ethereum signatures have no DER encoding"),
+            CryptoAlgorithm::Bls12381 => bail!("BLS12-381 signatures have no DER encoding"),
+            CryptoAlgorithm::MlDsa65 => bail!("ML-DSA signatures have no DER encoding"),
+            CryptoAlgorithm::X25519 | CryptoAlgorithm::Secp256r1Ecdh => {
+                bail!("{} is not a signature algorithm", algorithm)
+            }
         }
     }
 
@@ -74,9 +119,35 @@ impl Signature {
             Signature::Ed25519(_) => CryptoAlgorithm::Ed25519,
             Signature::Secp256k1(_) => CryptoAlgorithm::Secp256k1,
             Signature::Secp256r1(_) => CryptoAlgorithm::Secp256r1,
+            Signature::WebAuthn(_) => CryptoAlgorithm::WebAuthn,
+            Signature::Ethereum(_) => CryptoAlgorithm::Ethereum,
+            Signature::Bls12381(_) => CryptoAlgorithm::Bls12381,
+            Signature::MlDsa65(_) => CryptoAlgorithm::MlDsa65,
             Signature::Placeholder => CryptoAlgorithm::Ed25519,
         }
     }
+
+    /// Aggregates BLS12-381 signatures into a single signature, which can be
+    /// verified with [`VerifyingKey::verify_aggregate`] or
+    /// [`VerifyingKey::verify_aggregate_messages`].
+    ///
+    /// [`VerifyingKey::verify_aggregate`]: crate::VerifyingKey::verify_aggregate
+    /// [`VerifyingKey::verify_aggregate_messages`]: crate::VerifyingKey::verify_aggregate_messages
+    pub fn aggregate(signatures: &[Signature]) -> Result<Self> {
+        if signatures.is_empty() {
+            bail!("Cannot aggregate zero signatures");
+        }
+        let signatures = signatures
+            .iter()
+            .map(|signature| match signature {
+                Signature::Bls12381(signature) => Ok(signature),
+                _ => bail!("{} signatures cannot be aggregated", signature.algorithm()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Signature::Bls12381(Bls12381Signature::aggregate(
+            signatures,
+        )))
+    }
 }
 
 impl TryFrom<CryptoPayload> for Signature {
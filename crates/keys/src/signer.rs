@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use std::{fs, io::Write, path::Path};
+
+use crate::{CryptoAlgorithm, Signature, SigningKey, VerifyingKey};
+
+/// Produces signatures without exposing the signing key, which may be held
+/// in memory, in a key file or by a remote signing service.
+pub trait Signer: Send + Sync {
+    fn sign(&self, message: &[u8]) -> Result<Signature>;
+
+    fn verifying_key(&self) -> VerifyingKey;
+
+    fn algorithm(&self) -> CryptoAlgorithm {
+        self.verifying_key().algorithm()
+    }
+}
+
+impl Signer for SigningKey {
+    fn sign(&self, message: &[u8]) -> Result<Signature> {
+        Ok(SigningKey::sign(self, message))
+    }
+
+    fn verifying_key(&self) -> VerifyingKey {
+        SigningKey::verifying_key(self)
+    }
+
+    fn algorithm(&self) -> CryptoAlgorithm {
+        SigningKey::algorithm(self)
+    }
+}
+
+/// Signer of a key stored in a PEM encoded, encrypted PKCS#8 file. The key
+/// is decrypted when the file is opened and held in memory afterwards. To
+/// keep the key out of a process, serve it from another process to the
+/// remote signer of the prover.
+pub struct KeyFileSigner {
+    signing_key: SigningKey,
+}
+
+impl KeyFileSigner {
+    /// Reads and decrypts the key file at the path.
+    pub fn open(path: impl AsRef<Path>, password: &[u8]) -> Result<Self> {
+        let path = path.as_ref();
+        let pem = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read key file {}", path.display()))?;
+        let signing_key = SigningKey::from_encrypted_pkcs8_pem(&pem, password)
+            .with_context(|| format!("Failed to decrypt key file {}", path.display()))?;
+        Ok(KeyFileSigner { signing_key })
+    }
+
+    /// Encrypts the key with the password and writes it to a new key file,
+    /// which is only readable by the owner. Existing files are not replaced.
+    pub fn create(
+        path: impl AsRef<Path>,
+        signing_key: SigningKey,
+        password: &[u8],
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let pem = signing_key.to_encrypted_pkcs8_pem(password)?;
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        options
+            .open(path)
+            .and_then(|mut file| file.write_all(pem.as_bytes()))
+            .with_context(|| format!("Failed to write key file {}", path.display()))?;
+        Ok(KeyFileSigner { signing_key })
+    }
+}
+
+impl Signer for KeyFileSigner {
+    fn sign(&self, message: &[u8]) -> Result<Signature> {
+        Signer::sign(&self.signing_key, message)
+    }
+
+    fn verifying_key(&self) -> VerifyingKey {
+        self.signing_key.verifying_key()
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use ed25519_consensus::SigningKey as Ed25519SigningKey;
 use p256::ecdsa::{
     signature::DigestSigner, Signature as Secp256r1Signature, SigningKey as Secp256r1SigningKey,
@@ -8,13 +8,23 @@ use secp256k1::{Message as Secp256k1Message, SecretKey as Secp256k1SigningKey, S
 
 use sha2::Digest as _;
 
-use crate::{payload::CryptoPayload, CryptoAlgorithm, Signature, VerifyingKey};
+use crate::{
+    encoding::{self, KeyCurve},
+    ethereum, openssh,
+    payload::CryptoPayload,
+    Bls12381SigningKey, CryptoAlgorithm, DerivationPath, ExtendedSigningKey, Jwk,
+    MlDsa65SigningKey, Signature, VerifyingKey,
+};
 
 #[derive(Clone, Debug)]
 pub enum SigningKey {
     Ed25519(Box<Ed25519SigningKey>),
     Secp256k1(Secp256k1SigningKey),
     Secp256r1(Secp256r1SigningKey),
+    /// Secp256k1 key of an Ethereum wallet, signing `personal_sign` messages
+    Ethereum(Secp256k1SigningKey),
+    Bls12381(Box<Bls12381SigningKey>),
+    MlDsa65(MlDsa65SigningKey),
 }
 
 impl SigningKey {
@@ -30,11 +40,54 @@ impl SigningKey {
         SigningKey::Secp256r1(Secp256r1SigningKey::random(&mut OsRng))
     }
 
+    pub fn new_ethereum() -> Self {
+        SigningKey::Ethereum(Secp256k1SigningKey::new(&mut OsRng))
+    }
+
+    pub fn new_bls12381() -> Self {
+        SigningKey::Bls12381(Box::new(Bls12381SigningKey::random()))
+    }
+
+    pub fn new_ml_dsa_65() -> Self {
+        SigningKey::MlDsa65(MlDsa65SigningKey::random())
+    }
+
+    /// Derives the key at the path from a seed, see [`ExtendedSigningKey`].
+    pub fn derive_from_seed(
+        algorithm: CryptoAlgorithm,
+        seed: &[u8],
+        path: &DerivationPath,
+    ) -> Result<Self> {
+        let master_key = ExtendedSigningKey::from_seed(algorithm, seed)?;
+        Ok(master_key.derive_path(path)?.signing_key())
+    }
+
+    /// Derives the key at the path from a BIP-39 mnemonic and passphrase,
+    /// so that all keys of a user can be restored from the mnemonic.
+    pub fn derive_from_mnemonic(
+        algorithm: CryptoAlgorithm,
+        mnemonic: &str,
+        passphrase: &str,
+        path: &DerivationPath,
+    ) -> Result<Self> {
+        let master_key = ExtendedSigningKey::from_mnemonic(algorithm, mnemonic, passphrase)?;
+        Ok(master_key.derive_path(path)?.signing_key())
+    }
+
     pub fn new_with_algorithm(algorithm: CryptoAlgorithm) -> Result<Self> {
         match algorithm {
             CryptoAlgorithm::Ed25519 => Ok(SigningKey::new_ed25519()),
             CryptoAlgorithm::Secp256k1 => Ok(SigningKey::new_secp256k1()),
             CryptoAlgorithm::Secp256r1 => Ok(SigningKey::new_secp256r1()),
+            CryptoAlgorithm::Ethereum => Ok(SigningKey::new_ethereum()),
+            CryptoAlgorithm::Bls12381 => Ok(SigningKey::new_bls12381()),
+            CryptoAlgorithm::MlDsa65 => Ok(SigningKey::new_ml_dsa_65()),
+            CryptoAlgorithm::X25519 | CryptoAlgorithm::Secp256r1Ecdh => {
+                bail!("{} is not a signature algorithm", algorithm)
+            }
+            CryptoAlgorithm::WebAuthn => {
+                bail!("WebAuthn signatures are made with Secp256r1 keys")
+            }
         }
     }
 
@@ -45,8 +98,10 @@ impl SigningKey {
     pub fn to_bytes(&self) -> Vec<u8> {
         match self {
             SigningKey::Ed25519(sk) => sk.to_bytes().to_vec(),
-            SigningKey::Secp256k1(sk) => sk.secret_bytes().to_vec(),
+            SigningKey::Secp256k1(sk) | SigningKey::Ethereum(sk) => sk.secret_bytes().to_vec(),
             SigningKey::Secp256r1(sk) => sk.to_bytes().to_vec(),
+            SigningKey::Bls12381(sk) => sk.to_bytes().to_vec(),
+            SigningKey::MlDsa65(sk) => sk.to_bytes().to_vec(),
         }
     }
 
@@ -61,14 +116,136 @@ impl SigningKey {
             CryptoAlgorithm::Secp256r1 => Secp256r1SigningKey::from_slice(bytes)
                 .map(SigningKey::Secp256r1)
                 .map_err(|e| e.into()),
+            CryptoAlgorithm::Ethereum => Secp256k1SigningKey::from_slice(bytes)
+                .map(SigningKey::Ethereum)
+                .map_err(|e| e.into()),
+            CryptoAlgorithm::Bls12381 => {
+                Bls12381SigningKey::from_bytes(bytes).map(|sk| SigningKey::Bls12381(Box::new(sk)))
+            }
+            CryptoAlgorithm::MlDsa65 => {
+                MlDsa65SigningKey::from_bytes(bytes).map(SigningKey::MlDsa65)
+            }
+            CryptoAlgorithm::X25519 | CryptoAlgorithm::Secp256r1Ecdh => {
+                bail!("{} is not a signature algorithm", algorithm)
+            }
+            CryptoAlgorithm::WebAuthn => {
+                bail!("WebAuthn signatures are made with Secp256r1 keys")
+            }
         }
     }
 
+    /// Returns the DER encoded PKCS#8 PrivateKeyInfo of the key.
+    pub fn to_pkcs8_der(&self) -> Result<Vec<u8>> {
+        encoding::encode_pkcs8(
+            KeyCurve::from_algorithm(self.algorithm())?,
+            &self.to_bytes(),
+            &self.verifying_key().to_uncompressed_bytes(),
+        )
+    }
+
+    /// Returns the PEM encoded PKCS#8 PrivateKeyInfo of the key.
+    pub fn to_pkcs8_pem(&self) -> Result<String> {
+        encoding::encode_pem(encoding::PRIVATE_KEY_PEM_LABEL, &self.to_pkcs8_der()?)
+    }
+
+    /// Returns the DER encoded PKCS#8 EncryptedPrivateKeyInfo of the key,
+    /// encrypted with a key derived from the password.
+    pub fn to_encrypted_pkcs8_der(&self, password: &[u8]) -> Result<Vec<u8>> {
+        encoding::encode_encrypted_pkcs8(
+            KeyCurve::from_algorithm(self.algorithm())?,
+            &self.to_bytes(),
+            &self.verifying_key().to_uncompressed_bytes(),
+            password,
+        )
+    }
+
+    /// Returns the PEM encoded PKCS#8 EncryptedPrivateKeyInfo of the key.
+    pub fn to_encrypted_pkcs8_pem(&self, password: &[u8]) -> Result<String> {
+        encoding::encode_pem(
+            encoding::ENCRYPTED_PRIVATE_KEY_PEM_LABEL,
+            &self.to_encrypted_pkcs8_der(password)?,
+        )
+    }
+
+    /// Parses a DER encoded PKCS#8 PrivateKeyInfo. The algorithm is detected
+    /// from the OIDs of the encoding.
+    pub fn from_pkcs8_der(bytes: &[u8]) -> Result<Self> {
+        let (curve, private_key) = encoding::decode_pkcs8(bytes)?;
+        SigningKey::from_algorithm_and_bytes(curve.signature_algorithm()?, &private_key)
+    }
+
+    /// Parses a PEM encoded PKCS#8 PrivateKeyInfo. See
+    /// [`SigningKey::from_pkcs8_der`].
+    pub fn from_pkcs8_pem(pem: &str) -> Result<Self> {
+        let (label, der) = encoding::decode_pem(pem)?;
+        match label.as_str() {
+            encoding::PRIVATE_KEY_PEM_LABEL => SigningKey::from_pkcs8_der(&der),
+            encoding::ENCRYPTED_PRIVATE_KEY_PEM_LABEL => {
+                bail!("Private key is encrypted, a password is required")
+            }
+            _ => bail!("Unexpected PEM label {}", label),
+        }
+    }
+
+    /// Decrypts and parses a DER encoded PKCS#8 EncryptedPrivateKeyInfo. See
+    /// [`SigningKey::from_pkcs8_der`].
+    pub fn from_encrypted_pkcs8_der(bytes: &[u8], password: &[u8]) -> Result<Self> {
+        let (curve, private_key) = encoding::decode_encrypted_pkcs8(bytes, password)?;
+        SigningKey::from_algorithm_and_bytes(curve.signature_algorithm()?, &private_key)
+    }
+
+    /// Decrypts and parses a PEM encoded PKCS#8 EncryptedPrivateKeyInfo. See
+    /// [`SigningKey::from_pkcs8_der`].
+    pub fn from_encrypted_pkcs8_pem(pem: &str, password: &[u8]) -> Result<Self> {
+        let der = encoding::decode_pem_with_label(pem, encoding::ENCRYPTED_PRIVATE_KEY_PEM_LABEL)?;
+        SigningKey::from_encrypted_pkcs8_der(&der, password)
+    }
+
+    /// Returns the key in the unencrypted OpenSSH private key format, as
+    /// written by `ssh-keygen`. Only Ed25519 and Secp256r1 keys are
+    /// supported by OpenSSH.
+    pub fn to_openssh(&self) -> Result<String> {
+        openssh::encode_private_key(
+            self.algorithm(),
+            &self.to_bytes(),
+            &self.verifying_key().to_uncompressed_bytes(),
+        )
+    }
+
+    /// Parses an unencrypted key in the OpenSSH private key format.
+    pub fn from_openssh(armored: &str) -> Result<Self> {
+        let (algorithm, private_key) = openssh::decode_private_key(armored)?;
+        SigningKey::from_algorithm_and_bytes(algorithm, &private_key)
+    }
+
+    /// Returns the key as private JSON Web Key.
+    pub fn to_jwk(&self) -> Result<Jwk> {
+        Jwk::new(
+            self.algorithm(),
+            &self.verifying_key().to_uncompressed_bytes(),
+            Some(&self.to_bytes()),
+        )
+    }
+
+    /// Parses a private JSON Web Key, ensuring that its public members
+    /// match the private key.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        let signing_key =
+            SigningKey::from_algorithm_and_bytes(jwk.algorithm()?, &jwk.private_key_bytes()?)?;
+        if signing_key.verifying_key() != VerifyingKey::from_jwk(jwk)? {
+            bail!("JWK public key does not match its private key");
+        }
+        Ok(signing_key)
+    }
+
     pub fn algorithm(&self) -> CryptoAlgorithm {
         match self {
             SigningKey::Ed25519(_) => CryptoAlgorithm::Ed25519,
             SigningKey::Secp256k1(_) => CryptoAlgorithm::Secp256k1,
             SigningKey::Secp256r1(_) => CryptoAlgorithm::Secp256r1,
+            SigningKey::Ethereum(_) => CryptoAlgorithm::Ethereum,
+            SigningKey::Bls12381(_) => CryptoAlgorithm::Bls12381,
+            SigningKey::MlDsa65(_) => CryptoAlgorithm::MlDsa65,
         }
     }
 
@@ -87,8 +264,24 @@ impl SigningKey {
                 let sig: Secp256r1Signature = sk.sign_digest(digest);
                 Signature::Secp256r1(sig)
             }
+            SigningKey::Ethereum(sk) => Signature::Ethereum(ethereum::sign(sk, message)),
+            SigningKey::Bls12381(sk) => Signature::Bls12381(sk.sign(message)),
+            SigningKey::MlDsa65(sk) => Signature::MlDsa65(Box::new(sk.sign(message))),
         }
     }
+
+    /// Returns a proof that the key is held, which must be checked with
+    /// [`VerifyingKey::verify_possession`] before the verifying key takes part
+    /// in same-message aggregate signatures. Only BLS12-381 keys support this.
+    pub fn prove_possession(&self) -> Result<Signature> {
+        let SigningKey::Bls12381(sk) = self else {
+            bail!(
+                "{} keys do not support proofs of possession",
+                self.algorithm()
+            );
+        };
+        Ok(Signature::Bls12381(sk.prove_possession()))
+    }
 }
 
 impl PartialEq for SigningKey {
@@ -97,6 +290,9 @@ impl PartialEq for SigningKey {
             (SigningKey::Ed25519(a), SigningKey::Ed25519(b)) => a.as_bytes() == b.as_bytes(),
             (SigningKey::Secp256k1(a), SigningKey::Secp256k1(b)) => a == b,
             (SigningKey::Secp256r1(a), SigningKey::Secp256r1(b)) => a == b,
+            (SigningKey::Ethereum(a), SigningKey::Ethereum(b)) => a == b,
+            (SigningKey::Bls12381(a), SigningKey::Bls12381(b)) => a == b,
+            (SigningKey::MlDsa65(a), SigningKey::MlDsa65(b)) => a == b,
             _ => false,
         }
     }
@@ -1,11 +1,8 @@
 use anyhow::{anyhow, bail, Result};
 use ed25519_consensus::{SigningKey as Ed25519SigningKey, VerificationKey as Ed25519VerifyingKey};
-use p256::{
-    ecdsa::{
-        signature::DigestVerifier, SigningKey as Secp256r1SigningKey,
-        VerifyingKey as Secp256r1VerifyingKey,
-    },
-    pkcs8::{DecodePublicKey, EncodePublicKey},
+use p256::ecdsa::{
+    signature::DigestVerifier, SigningKey as Secp256r1SigningKey,
+    VerifyingKey as Secp256r1VerifyingKey,
 };
 use secp256k1::{
     Message as Secp256k1Message, PublicKey as Secp256k1VerifyingKey,
@@ -24,7 +21,13 @@ use utoipa::{
     PartialSchema, ToSchema,
 };
 
-use crate::{payload::CryptoPayload, CryptoAlgorithm, Signature, SigningKey};
+use crate::{
+    encoding::{self, KeyCurve},
+    ethereum, openssh,
+    payload::CryptoPayload,
+    Bls12381VerifyingKey, CryptoAlgorithm, EthereumAddress, Fingerprint, Jwk, MlDsa65VerifyingKey,
+    Signature, SigningKey,
+};
 use prism_serde::base64::{FromBase64, ToBase64};
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -37,6 +40,12 @@ pub enum VerifyingKey {
     Ed25519(Ed25519VerifyingKey),
     // TLS, X.509 PKI, Passkeys
     Secp256r1(Secp256r1VerifyingKey),
+    /// Ethereum wallets, identified by address
+    Ethereum(EthereumAddress),
+    /// Ethereum consensus, aggregated multi-signatures
+    Bls12381(Bls12381VerifyingKey),
+    /// Post-quantum
+    MlDsa65(MlDsa65VerifyingKey),
 }
 
 impl Hash for VerifyingKey {
@@ -54,6 +63,18 @@ impl Hash for VerifyingKey {
                 state.write_u8(2);
                 self.to_bytes().hash(state);
             }
+            VerifyingKey::Ethereum(_) => {
+                state.write_u8(3);
+                self.to_bytes().hash(state);
+            }
+            VerifyingKey::Bls12381(_) => {
+                state.write_u8(4);
+                self.to_bytes().hash(state);
+            }
+            VerifyingKey::MlDsa65(_) => {
+                state.write_u8(5);
+                self.to_bytes().hash(state);
+            }
         }
     }
 }
@@ -65,16 +86,70 @@ impl VerifyingKey {
             VerifyingKey::Ed25519(vk) => vk.to_bytes().to_vec(),
             VerifyingKey::Secp256k1(vk) => vk.serialize().to_vec(),
             VerifyingKey::Secp256r1(vk) => vk.to_sec1_bytes().to_vec(),
+            VerifyingKey::Ethereum(address) => address.as_bytes().to_vec(),
+            VerifyingKey::Bls12381(vk) => vk.to_bytes().to_vec(),
+            VerifyingKey::MlDsa65(vk) => vk.to_bytes(),
+        }
+    }
+
+    /// Returns the SEC1 encoding of the public key with uncompressed points
+    /// for keys on Weierstrass curves, and the raw bytes otherwise.
+    pub(crate) fn to_uncompressed_bytes(&self) -> Vec<u8> {
+        match self {
+            VerifyingKey::Ed25519(vk) => vk.to_bytes().to_vec(),
+            VerifyingKey::Secp256k1(vk) => vk.serialize_uncompressed().to_vec(),
+            VerifyingKey::Secp256r1(vk) => vk.to_encoded_point(false).as_bytes().to_vec(),
+            VerifyingKey::Ethereum(address) => address.as_bytes().to_vec(),
+            VerifyingKey::Bls12381(vk) => vk.to_bytes().to_vec(),
+            VerifyingKey::MlDsa65(vk) => vk.to_bytes(),
         }
     }
 
+    /// Returns the DER encoded SubjectPublicKeyInfo of the public key.
     pub fn to_der(&self) -> Result<Vec<u8>> {
-        let der = match self {
-            VerifyingKey::Ed25519(_) => bail!("Ed25519 vk to DER format is not implemented"),
-            VerifyingKey::Secp256k1(_) => bail!("Secp256k1 vk to DER format is not implemented"),
-            VerifyingKey::Secp256r1(vk) => vk.to_public_key_der()?.into_vec(),
-        };
-        Ok(der)
+        let curve = KeyCurve::from_algorithm(self.algorithm())?;
+        encoding::encode_spki(curve, &self.to_uncompressed_bytes())
+    }
+
+    /// Returns the PEM encoded SubjectPublicKeyInfo of the public key.
+    pub fn to_pem(&self) -> Result<String> {
+        encoding::encode_pem(encoding::PUBLIC_KEY_PEM_LABEL, &self.to_der()?)
+    }
+
+    /// Parses a DER encoded SubjectPublicKeyInfo. The algorithm is detected
+    /// from the OIDs of the encoding.
+    pub fn from_der(bytes: &[u8]) -> Result<Self> {
+        let (curve, public_key) = encoding::decode_spki(bytes)?;
+        VerifyingKey::from_algorithm_and_bytes(curve.signature_algorithm()?, &public_key)
+    }
+
+    /// Parses a PEM encoded SubjectPublicKeyInfo. See [`VerifyingKey::from_der`].
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let der = encoding::decode_pem_with_label(pem, encoding::PUBLIC_KEY_PEM_LABEL)?;
+        VerifyingKey::from_der(&der)
+    }
+
+    /// Returns the key as OpenSSH public key line, e.g. `ssh-ed25519 AAAA...`.
+    /// Only Ed25519 and Secp256r1 keys are supported by OpenSSH.
+    pub fn to_openssh(&self) -> Result<String> {
+        openssh::encode_public_key_line(self.algorithm(), &self.to_uncompressed_bytes())
+    }
+
+    /// Parses an OpenSSH public key line, as found in `authorized_keys` and
+    /// `.pub` files. A trailing comment is ignored.
+    pub fn from_openssh(line: &str) -> Result<Self> {
+        let (algorithm, public_key) = openssh::decode_public_key_line(line)?;
+        VerifyingKey::from_algorithm_and_bytes(algorithm, &public_key)
+    }
+
+    /// Returns the key as public JSON Web Key.
+    pub fn to_jwk(&self) -> Result<Jwk> {
+        Jwk::new(self.algorithm(), &self.to_uncompressed_bytes(), None)
+    }
+
+    /// Parses a JSON Web Key. Private members of the JWK are ignored.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        VerifyingKey::from_algorithm_and_bytes(jwk.algorithm()?, &jwk.public_key_bytes()?)
     }
 
     pub fn from_algorithm_and_bytes(algorithm: CryptoAlgorithm, bytes: &[u8]) -> Result<Self> {
@@ -88,17 +163,36 @@ impl VerifyingKey {
             CryptoAlgorithm::Secp256r1 => Secp256r1VerifyingKey::from_sec1_bytes(bytes)
                 .map(VerifyingKey::Secp256r1)
                 .map_err(|e| e.into()),
+            CryptoAlgorithm::Ethereum => {
+                EthereumAddress::from_slice(bytes).map(VerifyingKey::Ethereum)
+            }
+            CryptoAlgorithm::Bls12381 => {
+                Bls12381VerifyingKey::from_bytes(bytes).map(VerifyingKey::Bls12381)
+            }
+            CryptoAlgorithm::MlDsa65 => {
+                MlDsa65VerifyingKey::from_bytes(bytes).map(VerifyingKey::MlDsa65)
+            }
+            CryptoAlgorithm::X25519 | CryptoAlgorithm::Secp256r1Ecdh => {
+                bail!("{} is not a signature algorithm", algorithm)
+            }
+            CryptoAlgorithm::WebAuthn => {
+                bail!("WebAuthn signatures are made with Secp256r1 keys")
+            }
         }
     }
 
+    /// Parses a DER encoded SubjectPublicKeyInfo, ensuring that it contains
+    /// a key of the given algorithm.
     pub fn from_algorithm_and_der(algorithm: CryptoAlgorithm, bytes: &[u8]) -> Result<Self> {
-        match algorithm {
-            CryptoAlgorithm::Ed25519 => bail!("Ed25519 vk from DER format is not implemented"),
-            CryptoAlgorithm::Secp256k1 => bail!("Secp256k1 vk from DER format is not implemented"),
-            CryptoAlgorithm::Secp256r1 => Secp256r1VerifyingKey::from_public_key_der(bytes)
-                .map(VerifyingKey::Secp256r1)
-                .map_err(|e| e.into()),
+        let verifying_key = VerifyingKey::from_der(bytes)?;
+        if verifying_key.algorithm() != algorithm {
+            bail!(
+                "Expected {} key, got {}",
+                algorithm,
+                verifying_key.algorithm()
+            );
         }
+        Ok(verifying_key)
     }
 
     pub fn algorithm(&self) -> CryptoAlgorithm {
@@ -106,9 +200,18 @@ impl VerifyingKey {
             VerifyingKey::Ed25519(_) => CryptoAlgorithm::Ed25519,
             VerifyingKey::Secp256k1(_) => CryptoAlgorithm::Secp256k1,
             VerifyingKey::Secp256r1(_) => CryptoAlgorithm::Secp256r1,
+            VerifyingKey::Ethereum(_) => CryptoAlgorithm::Ethereum,
+            VerifyingKey::Bls12381(_) => CryptoAlgorithm::Bls12381,
+            VerifyingKey::MlDsa65(_) => CryptoAlgorithm::MlDsa65,
         }
     }
 
+    /// Returns the fingerprint of the key, which users compare out of band to
+    /// make sure that they see the same key. See [`Fingerprint`].
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::new(self)
+    }
+
     pub fn verify_signature(&self, message: &[u8], signature: &Signature) -> Result<()> {
         match self {
             VerifyingKey::Ed25519(vk) => {
@@ -129,18 +232,146 @@ impl VerifyingKey {
                 vk.verify(SECP256K1, &message, signature)
                     .map_err(|e| anyhow!("Failed to verify signature: {}", e))
             }
-            VerifyingKey::Secp256r1(vk) => {
-                let Signature::Secp256r1(signature) = signature else {
+            VerifyingKey::Secp256r1(vk) => match signature {
+                Signature::Secp256r1(signature) => {
+                    let mut digest = sha2::Sha256::new();
+                    digest.update(message);
+
+                    vk.verify_digest(digest, signature)
+                        .map_err(|e| anyhow!("Failed to verify signature: {}", e))
+                }
+                Signature::WebAuthn(signature) => signature.verify(vk, message),
+                _ => bail!("Invalid signature type"),
+            },
+            VerifyingKey::Ethereum(address) => {
+                let Signature::Ethereum(signature) = signature else {
                     bail!("Invalid signature type");
                 };
-                let mut digest = sha2::Sha256::new();
-                digest.update(message);
 
-                vk.verify_digest(digest, signature)
-                    .map_err(|e| anyhow!("Failed to verify signature: {}", e))
+                let signer = ethereum::recover(message, signature)?;
+                if EthereumAddress::from_public_key(&signer) != *address {
+                    bail!("Failed to verify signature: signed by another address");
+                }
+                Ok(())
+            }
+            VerifyingKey::Bls12381(vk) => {
+                let Signature::Bls12381(signature) = signature else {
+                    bail!("Invalid signature type");
+                };
+
+                vk.verify(message, signature)
+            }
+            VerifyingKey::MlDsa65(vk) => {
+                let Signature::MlDsa65(signature) = signature else {
+                    bail!("Invalid signature type");
+                };
+
+                vk.verify(message, signature)
             }
         }
     }
+
+    /// Verifies a proof of possession created with
+    /// [`SigningKey::prove_possession`]. Only BLS12-381 keys support this.
+    pub fn verify_possession(&self, proof: &Signature) -> Result<PopVerifiedKey> {
+        let (VerifyingKey::Bls12381(vk), Signature::Bls12381(bls_proof)) = (self, proof) else {
+            bail!(
+                "{} keys do not support proofs of possession",
+                self.algorithm()
+            );
+        };
+        vk.verify_possession(bls_proof)?;
+        Ok(PopVerifiedKey {
+            key: self.clone(),
+            proof: proof.clone(),
+        })
+    }
+
+    /// Verifies an aggregate signature of the same message by all keys, see
+    /// [`Signature::aggregate`]. Only keys that proved possession are
+    /// accepted, otherwise a key crafted from the others could forge the
+    /// aggregate signature on its own.
+    pub fn verify_aggregate(
+        keys: &[PopVerifiedKey],
+        message: &[u8],
+        signature: &Signature,
+    ) -> Result<()> {
+        let Signature::Bls12381(signature) = signature else {
+            bail!("Invalid signature type");
+        };
+        let keys = keys.iter().map(|key| key.key.as_bls12381()).collect::<Result<Vec<_>>>()?;
+        Bls12381VerifyingKey::verify_aggregate(&keys, message, signature)
+    }
+
+    /// Verifies an aggregate signature of distinct messages, each signed by
+    /// its paired key. No proofs of possession are needed.
+    pub fn verify_aggregate_messages(
+        pairs: &[(VerifyingKey, &[u8])],
+        signature: &Signature,
+    ) -> Result<()> {
+        let Signature::Bls12381(signature) = signature else {
+            bail!("Invalid signature type");
+        };
+        let pairs = pairs
+            .iter()
+            .map(|(key, message)| Ok((key.as_bls12381()?, *message)))
+            .collect::<Result<Vec<_>>>()?;
+        Bls12381VerifyingKey::verify_aggregate_messages(&pairs, signature)
+    }
+
+    fn as_bls12381(&self) -> Result<Bls12381VerifyingKey> {
+        match self {
+            VerifyingKey::Bls12381(vk) => Ok(*vk),
+            _ => bail!(
+                "{} keys cannot verify aggregate signatures",
+                self.algorithm()
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(try_from = "PossessionProof", into = "PossessionProof")]
+/// A BLS12-381 verifying key whose proof of possession has been verified, see
+/// [`VerifyingKey::verify_possession`]. It is serialized along with the proof,
+/// which is verified again when deserializing.
+pub struct PopVerifiedKey {
+    key: VerifyingKey,
+    proof: Signature,
+}
+
+impl PopVerifiedKey {
+    pub fn verifying_key(&self) -> &VerifyingKey {
+        &self.key
+    }
+
+    pub fn proof(&self) -> &Signature {
+        &self.proof
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+/// Serialized form of [`PopVerifiedKey`], whose proof is not verified yet.
+struct PossessionProof {
+    key: VerifyingKey,
+    proof: Signature,
+}
+
+impl TryFrom<PossessionProof> for PopVerifiedKey {
+    type Error = anyhow::Error;
+
+    fn try_from(value: PossessionProof) -> Result<Self> {
+        value.key.verify_possession(&value.proof)
+    }
+}
+
+impl From<PopVerifiedKey> for PossessionProof {
+    fn from(key: PopVerifiedKey) -> Self {
+        PossessionProof {
+            key: key.key,
+            proof: key.proof,
+        }
+    }
 }
 
 impl TryFrom<CryptoPayload> for VerifyingKey {
@@ -178,6 +409,24 @@ impl From<Secp256r1VerifyingKey> for VerifyingKey {
     }
 }
 
+impl From<Bls12381VerifyingKey> for VerifyingKey {
+    fn from(vk: Bls12381VerifyingKey) -> Self {
+        VerifyingKey::Bls12381(vk)
+    }
+}
+
+impl From<MlDsa65VerifyingKey> for VerifyingKey {
+    fn from(vk: MlDsa65VerifyingKey) -> Self {
+        VerifyingKey::MlDsa65(vk)
+    }
+}
+
+impl From<EthereumAddress> for VerifyingKey {
+    fn from(address: EthereumAddress) -> Self {
+        VerifyingKey::Ethereum(address)
+    }
+}
+
 impl From<Ed25519SigningKey> for VerifyingKey {
     fn from(sk: Ed25519SigningKey) -> Self {
         VerifyingKey::Ed25519(sk.verification_key())
@@ -202,6 +451,11 @@ impl From<SigningKey> for VerifyingKey {
             SigningKey::Ed25519(sk) => (*sk).into(),
             SigningKey::Secp256k1(sk) => sk.into(),
             SigningKey::Secp256r1(sk) => sk.into(),
+            SigningKey::Ethereum(sk) => {
+                EthereumAddress::from_public_key(&sk.public_key(SECP256K1)).into()
+            }
+            SigningKey::Bls12381(sk) => sk.verifying_key().into(),
+            SigningKey::MlDsa65(sk) => sk.verifying_key().into(),
         }
     }
 }
@@ -219,6 +473,8 @@ impl FromBase64 for VerifyingKey {
     /// decode it and create a `VerifyingKey` instance. According to the specifications,
     /// the input string should be either [32 bytes (Ed25519)](https://datatracker.ietf.org/doc/html/rfc8032#section-5.1.5) or [33/65 bytes (Secp256k1 or Secp256r1)](https://www.secg.org/sec1-v2.pdf).
     /// The secp256k1 and secp256r1 keys can be either compressed (33 bytes) or uncompressed (65 bytes).
+    /// 20 bytes are interpreted as an Ethereum address, 48 bytes as a compressed BLS12-381 key
+    /// and 1952 bytes as an ML-DSA-65 key.
     ///
     /// # Returns
     ///
@@ -228,11 +484,18 @@ impl FromBase64 for VerifyingKey {
         let bytes = Vec::<u8>::from_base64(base64)?;
 
         match bytes.len() {
+            20 => Ok(VerifyingKey::Ethereum(EthereumAddress::from_slice(&bytes)?)),
             32 => {
                 let vk = Ed25519VerifyingKey::try_from(bytes.as_slice())
                     .map_err(|e| anyhow!("Invalid Ed25519 key: {}", e))?;
                 Ok(VerifyingKey::Ed25519(vk))
             }
+            48 => Ok(VerifyingKey::Bls12381(Bls12381VerifyingKey::from_bytes(
+                &bytes,
+            )?)),
+            1952 => Ok(VerifyingKey::MlDsa65(MlDsa65VerifyingKey::from_bytes(
+                &bytes,
+            )?)),
             33 | 65 => {
                 if let Ok(vk) = Secp256k1VerifyingKey::from_slice(bytes.as_slice()) {
                     Ok(VerifyingKey::Secp256k1(vk))
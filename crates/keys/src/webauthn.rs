@@ -0,0 +1,87 @@
+use anyhow::{anyhow, bail, Result};
+use p256::ecdsa::{
+    signature::DigestVerifier, Signature as Secp256r1Signature,
+    VerifyingKey as Secp256r1VerifyingKey,
+};
+use prism_serde::base64::FromBase64Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+/// Type of the client data of assertions, as opposed to registrations
+const ASSERTION_TYPE: &str = "webauthn.get";
+/// Length of the RP ID hash preceding the flags in authenticator data
+const RP_ID_HASH_LENGTH: usize = 32;
+/// Authenticator data flag signalling that the user was present
+const USER_PRESENT_FLAG: u8 = 0x01;
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ty: String,
+    challenge: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// WebAuthn assertion of a passkey (ES256). The authenticator does not sign
+/// the message itself, but `authenticatorData || SHA-256(clientDataJSON)`,
+/// where the client data contains the SHA-256 hash of the message as
+/// challenge.
+pub struct WebAuthnSignature {
+    /// Authenticator data, as returned by the authenticator
+    pub authenticator_data: Vec<u8>,
+    /// UTF-8 encoded client data JSON, as returned by the client
+    pub client_data_json: Vec<u8>,
+    /// ECDSA signature of the authenticator
+    pub signature: Secp256r1Signature,
+}
+
+impl WebAuthnSignature {
+    /// Creates a WebAuthn signature from an assertion response, whose
+    /// signature is DER encoded.
+    pub fn from_der(
+        authenticator_data: &[u8],
+        client_data_json: &[u8],
+        signature: &[u8],
+    ) -> Result<Self> {
+        Ok(WebAuthnSignature {
+            authenticator_data: authenticator_data.to_vec(),
+            client_data_json: client_data_json.to_vec(),
+            signature: Secp256r1Signature::from_der(signature)?,
+        })
+    }
+
+    /// Returns the challenge that the client data must contain when signing
+    /// the message.
+    pub fn challenge(message: &[u8]) -> [u8; 32] {
+        Sha256::digest(message).into()
+    }
+
+    /// Verifies that the assertion is for the message and was signed by the
+    /// key. Origin and relying party are not checked, because accounts are
+    /// not bound to either.
+    pub(crate) fn verify(&self, vk: &Secp256r1VerifyingKey, message: &[u8]) -> Result<()> {
+        let client_data: ClientData = serde_json::from_slice(&self.client_data_json)
+            .map_err(|e| anyhow!("Invalid WebAuthn client data: {}", e))?;
+        if client_data.ty != ASSERTION_TYPE {
+            bail!("Invalid WebAuthn client data type {}", client_data.ty);
+        }
+        let challenge = Vec::<u8>::from_base64_url(&client_data.challenge)
+            .map_err(|e| anyhow!("Invalid WebAuthn challenge: {}", e))?;
+        if challenge != Self::challenge(message) {
+            bail!("WebAuthn challenge does not match the message");
+        }
+
+        let Some(flags) = self.authenticator_data.get(RP_ID_HASH_LENGTH) else {
+            bail!("WebAuthn authenticator data is too short");
+        };
+        if flags & USER_PRESENT_FLAG == 0 {
+            bail!("WebAuthn assertion was made without user presence");
+        }
+
+        let mut digest = Sha256::new();
+        digest.update(&self.authenticator_data);
+        digest.update(Sha256::digest(&self.client_data_json));
+        vk.verify_digest(digest, &self.signature)
+            .map_err(|e| anyhow!("Failed to verify signature: {}", e))
+    }
+}
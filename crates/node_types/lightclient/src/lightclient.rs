@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use prism_common::digest::Digest;
+use prism_common::{digest::Digest, params::ProtocolParams};
 use prism_da::DataAvailabilityLayer;
 use prism_errors::{DataAvailabilityError, GeneralError};
 use prism_keys::VerifyingKey;
@@ -18,6 +18,8 @@ pub struct LightClient {
     pub sp1_vkey: String,
     /// The height to start syncing from.
     pub start_height: u64,
+    /// The parameters of the network, which every epoch proof has to commit to.
+    pub params: ProtocolParams,
 }
 
 #[allow(dead_code)]
@@ -27,12 +29,14 @@ impl LightClient {
         start_height: u64,
         prover_pubkey: Option<VerifyingKey>,
         sp1_vkey: String,
+        params: ProtocolParams,
     ) -> LightClient {
         LightClient {
             da,
             sp1_vkey,
             prover_pubkey,
             start_height,
+            params,
         }
     }
 
@@ -55,6 +59,8 @@ impl LightClient {
         let start_height = self.start_height;
         spawn(async move {
             let mut current_position = start_height;
+            let params_digest =
+                self.params.digest().expect("Protocol parameters should be serializable");
             let mut height_rx = self.da.subscribe_to_heights();
 
             loop {
@@ -68,7 +74,7 @@ impl LightClient {
 
                                     // TODO: Issue #144
                                     if let Some(pubkey) = &self.prover_pubkey {
-                                        match finalized_epoch.verify_signature(pubkey.clone()) {
+                                        match finalized_epoch.verify_signature(pubkey.clone(), &self.params.chain_id) {
                                             Ok(_) => trace!(
                                                 "valid signature for epoch {}",
                                                 finalized_epoch.height
@@ -84,8 +90,8 @@ impl LightClient {
                                     let current_commitment = &finalized_epoch.current_commitment;
                                     let public_values = finalized_epoch.proof.public_values.clone();
 
-                                    if public_values.as_slice().len() < 64 {
-                                        panic!("public_values length is less than 64 bytes in epoch {}", finalized_epoch.height);
+                                    if public_values.as_slice().len() < 104 {
+                                        panic!("public_values length is less than 104 bytes in epoch {}", finalized_epoch.height);
                                     }
 
                                     let mut slice = [0u8; 32];
@@ -107,6 +113,28 @@ impl LightClient {
                                         panic!("Commitment mismatch in epoch {}", finalized_epoch.height);
                                     }
 
+                                    // the batch must have been validated against the epoch it is posted for
+                                    let mut slice = [0u8; 8];
+                                    slice.copy_from_slice(&public_values.as_slice()[64..72]);
+                                    let proof_epoch = u64::from_le_bytes(slice);
+                                    if proof_epoch != finalized_epoch.height {
+                                        panic!(
+                                            "Epoch mismatch: expected {}, proof was generated for epoch {}",
+                                            finalized_epoch.height, proof_epoch
+                                        );
+                                    }
+
+                                    // the prover must not have changed the limits of the network
+                                    let mut slice = [0u8; 32];
+                                    slice.copy_from_slice(&public_values.as_slice()[72..104]);
+                                    let proof_params_digest = Digest::from(slice);
+                                    if proof_params_digest != params_digest {
+                                        panic!(
+                                            "Protocol parameter mismatch in epoch {}: expected {:?}, got {:?}",
+                                            finalized_epoch.height, params_digest, proof_params_digest
+                                        );
+                                    }
+
                                     // SNARK verification
                                     #[cfg(feature = "mock_prover")]
                                     info!("mock_prover is activated, skipping proof verification");
@@ -1,4 +1,5 @@
 pub mod prover;
+pub mod remote_signer;
 pub mod webserver;
 
 pub use prover::{Config, Prover};
@@ -1,8 +1,13 @@
 use anyhow::{anyhow, bail, Context, Result};
-use jmt::KeyHash;
-use prism_common::{account::Account, digest::Digest, transaction::Transaction};
-use prism_errors::DataAvailabilityError;
-use prism_keys::{CryptoAlgorithm, SigningKey, VerifyingKey};
+use jmt::{JellyfishMerkleTree, KeyHash};
+use prism_common::{
+    account::Account,
+    digest::Digest,
+    params::{ProtocolParams, ValidationContext},
+    transaction::Transaction,
+};
+use prism_errors::{DataAvailabilityError, DatabaseError};
+use prism_keys::{CryptoAlgorithm, Signer, SigningKey, VerifyingKey};
 use prism_storage::database::Database;
 pub use prism_tree::AccountResponse;
 use prism_tree::{
@@ -15,11 +20,10 @@ use prism_tree::{
 use std::{self, collections::VecDeque, sync::Arc};
 use tokio::{
     sync::{broadcast, RwLock},
-    task::JoinSet,
+    task::{spawn_blocking, JoinSet},
 };
 
 use crate::webserver::{WebServer, WebServerConfig};
-use prism_common::operation::Operation;
 use prism_da::{DataAvailabilityLayer, FinalizedEpoch};
 use sp1_sdk::{CpuProver, Prover as _, ProverClient, SP1ProvingKey, SP1Stdin, SP1VerifyingKey};
 
@@ -39,15 +43,19 @@ pub struct Config {
     /// Configuration for the webserver.
     pub webserver: WebServerConfig,
 
-    /// Key used to sign new [`FinalizedEpochs`].
-    pub signing_key: SigningKey,
+    /// Signer of new [`FinalizedEpochs`]. Use a [`crate::remote_signer::RemoteSigner`]
+    /// to keep the key out of the prover process.
+    pub signer: Arc<dyn Signer>,
 
     /// Key used to verify incoming [`FinalizedEpochs`].
-    /// This is not necessarily the counterpart to signing_key, as fullnodes must use the [`verifying_key`] of the prover.
+    /// This is not necessarily the counterpart to the key of signer, as fullnodes must use the [`verifying_key`] of the prover.
     pub verifying_key: VerifyingKey,
 
     /// DA layer height the prover should start syncing transactions from.
     pub start_height: u64,
+
+    /// Parameters of the network that all transactions are validated against.
+    pub params: ProtocolParams,
 }
 
 impl Default for Config {
@@ -58,9 +66,10 @@ impl Default for Config {
             prover: true,
             batcher: true,
             webserver: WebServerConfig::default(),
-            signing_key: signing_key.clone(),
             verifying_key: signing_key.verifying_key(),
+            signer: Arc::new(signing_key),
             start_height: 1,
+            params: ProtocolParams::default(),
         }
     }
 }
@@ -82,9 +91,10 @@ impl Config {
             prover: true,
             batcher: true,
             webserver: WebServerConfig::default(),
-            signing_key: signing_key.clone(),
             verifying_key: signing_key.verifying_key(),
+            signer: Arc::new(signing_key),
             start_height: 1,
+            params: ProtocolParams::default(),
         })
     }
 }
@@ -124,7 +134,25 @@ impl Prover {
             }
         };
 
-        let tree = Arc::new(RwLock::new(KeyDirectoryTree::load(db.clone(), saved_epoch)));
+        // The tree is versioned per transaction, not per epoch
+        let tree_version = match db.get_tree_version(&saved_epoch) {
+            Ok(version) => version,
+            Err(e) => match e.downcast_ref::<DatabaseError>() {
+                // Nothing has been committed to a new database yet
+                Some(DatabaseError::NotFoundError(_)) if saved_epoch == 0 => 0,
+                // Databases of earlier versions don't store tree versions yet
+                Some(DatabaseError::NotFoundError(_)) => {
+                    info!("tree versions are missing, recovering them from the epoch commitments");
+                    backfill_tree_versions(&db, saved_epoch)?
+                }
+                _ => return Err(e.context("Failed to load tree version")),
+            },
+        };
+        let tree = Arc::new(RwLock::new(KeyDirectoryTree::load(
+            db.clone(),
+            tree_version,
+            cfg.params.clone(),
+        )));
 
         #[cfg(feature = "mock_prover")]
         let prover_client = ProverClient::builder().mock().build();
@@ -202,6 +230,7 @@ impl Prover {
         if saved_epoch == 0 {
             let initial_commitment = self.get_commitment().await?;
             self.db.set_commitment(&0, &initial_commitment)?;
+            self.db.set_tree_version(&0, &self.tree.read().await.version())?;
         }
 
         // TODO: Should be persisted in database for crash recovery
@@ -292,7 +321,7 @@ impl Prover {
 
         // TODO: Issue #144
         epoch
-            .verify_signature(self.cfg.verifying_key.clone())
+            .verify_signature(self.cfg.verifying_key.clone(), &self.cfg.params.chain_id)
             .with_context(|| format!("Invalid signature in epoch {}", epoch.height))?;
         trace!("valid signature for epoch {}", epoch.height);
 
@@ -315,7 +344,7 @@ impl Prover {
 
         let all_transactions: Vec<Transaction> = buffered_transactions.drain(..).collect();
         if !all_transactions.is_empty() {
-            self.execute_block(all_transactions).await?;
+            self.execute_block(all_transactions, epoch.height).await?;
         }
 
         let new_commitment = self.get_commitment().await?;
@@ -345,6 +374,7 @@ impl Prover {
 
         current_epoch += 1;
         self.db.set_commitment(&current_epoch, &new_commitment)?;
+        self.db.set_tree_version(&current_epoch, &self.tree.read().await.version())?;
         self.db.set_epoch(&current_epoch)?;
 
         Ok(())
@@ -352,13 +382,17 @@ impl Prover {
 
     // should only be called for testing and historical sync, it does not
     // generate the Batch object for proof generation.
-    async fn execute_block(&self, transactions: Vec<Transaction>) -> Result<Vec<Proof>> {
+    async fn execute_block(
+        &self,
+        transactions: Vec<Transaction>,
+        epoch_height: u64,
+    ) -> Result<Vec<Proof>> {
         debug!("executing block with {} transactions", transactions.len());
 
         let mut proofs = Vec::new();
 
         for transaction in transactions {
-            match self.process_transaction(transaction.clone()).await {
+            match self.process_transaction(transaction.clone(), epoch_height).await {
                 Ok(proof) => proofs.push(proof),
                 Err(e) => {
                     // Log the error and continue with the next transaction
@@ -379,7 +413,7 @@ impl Prover {
         transactions: Vec<Transaction>,
     ) -> Result<()> {
         let mut tree = self.tree.write().await;
-        let batch = tree.process_batch(transactions)?;
+        let batch = tree.process_batch(transactions, epoch_height)?;
         batch.verify()?;
 
         let finalized_epoch = self.prove_epoch(epoch_height, &batch).await?;
@@ -388,6 +422,7 @@ impl Prover {
 
         let new_epoch_height = epoch_height + 1;
         self.db.set_commitment(&new_epoch_height, &batch.new_root)?;
+        self.db.set_tree_version(&new_epoch_height, &tree.version())?;
         self.db.set_epoch(&new_epoch_height)?;
 
         info!("finalized new epoch at height {}", epoch_height);
@@ -419,8 +454,14 @@ impl Prover {
             signature: None,
         };
 
-        epoch_json.insert_signature(&self.cfg.signing_key);
-        Ok(epoch_json)
+        // Remote signers block on network I/O
+        let signer = self.cfg.signer.clone();
+        let chain_id = self.cfg.params.chain_id.clone();
+        spawn_blocking(move || -> Result<FinalizedEpoch> {
+            epoch_json.insert_signature(signer.as_ref(), &chain_id)?;
+            Ok(epoch_json)
+        })
+        .await?
     }
 
     async fn post_batch_loop(self: Arc<Self>) -> Result<()> {
@@ -472,10 +513,34 @@ impl Prover {
         tree.get(key_hash)
     }
 
+    /// Returns the account as of the start of the given epoch, with a proof
+    /// against the commitment of that epoch.
+    pub async fn get_account_at(&self, id: &str, epoch: u64) -> Result<AccountResponse> {
+        let commitment = self.db.get_commitment(&epoch)?;
+        let version = self.db.get_tree_version(&epoch)?;
+
+        let tree = self.tree.read().await;
+        let key_hash = KeyHash::with::<TreeHasher>(id);
+        let account_response = tree.get_at(key_hash, version)?;
+
+        let proof_root = match &account_response {
+            Found(_, proof) | Deactivated(_, proof) | NotFound(proof) => proof.root,
+        };
+        if proof_root != commitment {
+            bail!("State of epoch {} does not match its commitment", epoch);
+        }
+
+        Ok(account_response)
+    }
+
     /// Updates the state from an already verified pending transaction.
-    async fn process_transaction(&self, transaction: Transaction) -> Result<Proof> {
+    async fn process_transaction(
+        &self,
+        transaction: Transaction,
+        epoch_height: u64,
+    ) -> Result<Proof> {
         let mut tree = self.tree.write().await;
-        tree.process_transaction(transaction)
+        tree.process_transaction(transaction, epoch_height)
     }
 
     /// Adds an transaction to be posted to the DA layer and applied in the next epoch.
@@ -484,22 +549,36 @@ impl Prover {
             bail!("Batcher is disabled, cannot queue transactions");
         }
 
+        // the transaction will be applied in a later epoch, but it is at least
+        // required to be valid in the current one
+        let ctx = ValidationContext::new(self.db.get_epoch()?, self.cfg.params.clone());
+
         // validate against existing account if necessary, including signature checks
-        match transaction.operation {
-            Operation::RegisterService { .. } | Operation::CreateAccount { .. } => {
-                Account::default().process_transaction(&transaction)?;
+        match transaction.operations.first() {
+            None => bail!("Transaction contains no operations"),
+            Some(operation) if operation.is_creation() => {
+                Account::default().process_transaction(&transaction, &ctx)?;
             }
-            Operation::AddKey { .. }
-            | Operation::RevokeKey { .. }
-            | Operation::AddData { .. }
-            | Operation::SetData { .. } => {
+            Some(_) => {
                 let account_response = self.get_account(&transaction.id).await?;
 
-                let Found(mut account, _) = account_response else {
-                    bail!("Account not found for id: {}", transaction.id)
+                let mut account = match account_response {
+                    Found(account, _) => account,
+                    Deactivated(_, _) => bail!("Account {} is deactivated", transaction.id),
+                    NotFound(_) => bail!("Account not found for id: {}", transaction.id),
                 };
 
-                account.process_transaction(&transaction)?;
+                if transaction.delegated {
+                    let Some(service_id) = account.delegate() else {
+                        bail!("Account {} has not delegated to a service", transaction.id);
+                    };
+                    let Found(service, _) = self.get_account(service_id).await? else {
+                        bail!("Service not found for id: {}", service_id);
+                    };
+                    account.process_delegated_transaction(&transaction, &service, &ctx)?;
+                } else {
+                    account.process_transaction(&transaction, &ctx)?;
+                }
             }
         };
 
@@ -509,5 +588,32 @@ impl Prover {
     }
 }
 
+/// Recovers the tree versions of all epochs up to the saved epoch by matching
+/// the commitments of the epochs against the root hashes of the tree versions.
+/// Stores them in the database and returns the tree version of the saved epoch.
+fn backfill_tree_versions(db: &Arc<Box<dyn Database>>, saved_epoch: u64) -> Result<u64> {
+    let jmt = JellyfishMerkleTree::<Arc<Box<dyn Database>>, TreeHasher>::new(db.clone());
+
+    let mut version = 0;
+    for epoch in 0..=saved_epoch {
+        let commitment = db
+            .get_commitment(&epoch)
+            .with_context(|| format!("Failed to load commitment of epoch {}", epoch))?;
+        // Epochs without transactions share the version of the previous epoch
+        loop {
+            let root = jmt
+                .get_root_hash_option(version)
+                .map_err(|e| anyhow!("Failed to get root hash: {}", e))?;
+            match root {
+                Some(root) if Digest(root.0) == commitment => break,
+                Some(_) => version += 1,
+                None => bail!("No tree version matches the commitment of epoch {}", epoch),
+            }
+        }
+        db.set_tree_version(&epoch, &version)?;
+    }
+    Ok(version)
+}
+
 #[cfg(test)]
 mod tests;
@@ -58,10 +58,10 @@ async fn test_process_transactions(algorithm: CryptoAlgorithm) {
         .create_account_with_random_key_signed(algorithm, "test_account", "test_service")
         .commit();
 
-    let proof = prover.process_transaction(register_service_transaction).await.unwrap();
+    let proof = prover.process_transaction(register_service_transaction, 0).await.unwrap();
     assert!(matches!(proof, Proof::Insert(_)));
 
-    let proof = prover.process_transaction(create_account_transaction.clone()).await.unwrap();
+    let proof = prover.process_transaction(create_account_transaction.clone(), 0).await.unwrap();
     assert!(matches!(proof, Proof::Insert(_)));
 
     let new_key = SigningKey::new_with_algorithm(algorithm).expect("Failed to create new key");
@@ -69,7 +69,7 @@ async fn test_process_transactions(algorithm: CryptoAlgorithm) {
         .add_key_verified_with_root("test_account", new_key.clone().into())
         .commit();
 
-    let proof = prover.process_transaction(add_key_transaction).await.unwrap();
+    let proof = prover.process_transaction(add_key_transaction, 0).await.unwrap();
 
     assert!(matches!(proof, Proof::Update(_)));
 
@@ -77,11 +77,11 @@ async fn test_process_transactions(algorithm: CryptoAlgorithm) {
     let revoke_transaction = transaction_builder
         .revoke_key(
             "test_account",
-            create_account_transaction.operation.get_public_key().cloned().unwrap(),
+            create_account_transaction.operations[0].get_public_key().cloned().unwrap(),
             &new_key,
         )
         .commit();
-    let proof = prover.process_transaction(revoke_transaction).await.unwrap();
+    let proof = prover.process_transaction(revoke_transaction, 0).await.unwrap();
     assert!(matches!(proof, Proof::Update(_)));
 }
 
@@ -107,7 +107,7 @@ async fn test_execute_block_with_invalid_tx(algorithm: CryptoAlgorithm) {
         tx_builder.add_random_key(algorithm, "account_id", &new_key_1).build(),
     ];
 
-    let proofs = prover.execute_block(transactions).await.unwrap();
+    let proofs = prover.execute_block(transactions, 0).await.unwrap();
     assert_eq!(proofs.len(), 4);
 }
 
@@ -116,7 +116,7 @@ async fn test_execute_block(algorithm: CryptoAlgorithm) {
 
     let transactions = create_mock_transactions(algorithm, "test_service".to_string());
 
-    let proofs = prover.execute_block(transactions).await.unwrap();
+    let proofs = prover.execute_block(transactions, 0).await.unwrap();
     assert_eq!(proofs.len(), 4);
 }
 
@@ -210,6 +210,80 @@ async fn test_load_persisted_state(algorithm: CryptoAlgorithm) {
     );
 }
 
+async fn test_get_account_at(algorithm: CryptoAlgorithm) {
+    let prover = create_test_prover(algorithm).await;
+    let transactions = create_mock_transactions(algorithm, "test_service".to_string());
+    let (first_block, second_block) = transactions.split_at(2);
+
+    prover.db.set_commitment(&0, &prover.get_commitment().await.unwrap()).unwrap();
+    prover.db.set_tree_version(&0, &prover.tree.read().await.version()).unwrap();
+    prover.finalize_new_epoch(0, first_block.to_vec()).await.unwrap();
+    prover.finalize_new_epoch(1, second_block.to_vec()).await.unwrap();
+
+    let Found(old_account, proof) = prover.get_account_at("user1@example.com", 1).await.unwrap()
+    else {
+        panic!("Account should exist at epoch 1");
+    };
+    assert_eq!(proof.root, prover.db.get_commitment(&1).unwrap());
+    assert_eq!(old_account.valid_keys().len(), 1);
+
+    let Found(account, _) = prover.get_account_at("user1@example.com", 2).await.unwrap() else {
+        panic!("Account should exist at epoch 2");
+    };
+    assert_eq!(account.valid_keys().len(), 2);
+
+    let NotFound(_) = prover.get_account_at("user1@example.com", 0).await.unwrap() else {
+        panic!("Account should not exist at epoch 0");
+    };
+    assert!(prover.get_account_at("user1@example.com", 3).await.is_err());
+}
+
+async fn test_load_without_tree_version(algorithm: CryptoAlgorithm) {
+    let (da_layer, _rx, _brx) = InMemoryDataAvailabilityLayer::new(1);
+    let da_layer = Arc::new(da_layer);
+    let cfg = Config::default_with_key_algorithm(algorithm).unwrap();
+
+    // A new database starts from the empty tree
+    let db: Arc<Box<dyn Database>> = Arc::new(Box::new(InMemoryDatabase::new()));
+    assert!(Prover::new(db.clone(), da_layer.clone(), &cfg).is_ok());
+
+    // Databases with epochs but without tree versions or commitments cannot be loaded
+    db.set_epoch(&2).unwrap();
+    assert!(Prover::new(db.clone(), da_layer.clone(), &cfg).is_err());
+
+    db.set_tree_version(&2, &5).unwrap();
+    let prover = Prover::new(db, da_layer.clone(), &cfg).unwrap();
+    assert_eq!(prover.tree.read().await.version(), 5);
+
+    // Tree versions of databases of earlier versions are recovered from the
+    // commitments of their epochs
+    let db: Arc<Box<dyn Database>> = Arc::new(Box::new(InMemoryDatabase::new()));
+    let mut tree = KeyDirectoryTree::new(db.clone());
+    db.set_commitment(&0, &tree.get_commitment().unwrap()).unwrap();
+    let transactions = create_mock_transactions(algorithm, "test_service".to_string());
+    let mut versions = vec![tree.version()];
+    for (epoch, transactions) in [
+        (1, &transactions[..2]),
+        (2, &[][..]),
+        (3, &transactions[2..]),
+    ] {
+        tree.process_batch(transactions.to_vec(), epoch).unwrap();
+        db.set_commitment(&epoch, &tree.get_commitment().unwrap()).unwrap();
+        versions.push(tree.version());
+    }
+    db.set_epoch(&3).unwrap();
+
+    let prover = Prover::new(db.clone(), da_layer, &cfg).unwrap();
+    assert_eq!(prover.tree.read().await.version(), tree.version());
+    assert_eq!(
+        prover.get_commitment().await.unwrap(),
+        tree.get_commitment().unwrap()
+    );
+    for (epoch, version) in versions.into_iter().enumerate() {
+        assert_eq!(db.get_tree_version(&(epoch as u64)).unwrap(), version);
+    }
+}
+
 macro_rules! generate_algorithm_tests {
     ($test_fn:ident) => {
         paste::paste! {
@@ -238,3 +312,5 @@ generate_algorithm_tests!(test_execute_block);
 generate_algorithm_tests!(test_finalize_new_epoch);
 generate_algorithm_tests!(test_restart_sync_from_scratch);
 generate_algorithm_tests!(test_load_persisted_state);
+generate_algorithm_tests!(test_get_account_at);
+generate_algorithm_tests!(test_load_without_tree_version);
@@ -0,0 +1,352 @@
+use anyhow::{anyhow, bail, Context, Result};
+use prism_da::FinalizedEpoch;
+use prism_keys::{Signature, Signer, VerifyingKey};
+use prism_serde::{
+    binary::{FromBinary, ToBinary},
+    raw_or_b64,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    str::FromStr,
+    time::Duration,
+};
+#[cfg(unix)]
+use std::{
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+};
+
+/// Returns the JSON encoded verifying key of the signer
+const VERIFYING_KEY_PATH: &str = "/verifying_key";
+/// Signs the message of a JSON encoded [`SignRequest`], if it is an epoch
+const SIGN_PATH: &str = "/sign";
+
+/// Read and write timeout of connections to remote signers
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum size of requests and responses, including headers
+const MAX_MESSAGE_LENGTH: u64 = 16 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+struct SignRequest {
+    #[serde(with = "raw_or_b64")]
+    message: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SignResponse {
+    signature: Signature,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Address of a remote signer, `http://<host>:<port>` or `unix:<path>`.
+pub enum RemoteSignerEndpoint {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl RemoteSignerEndpoint {
+    fn request(&self, method: &str, path: &str, body: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            RemoteSignerEndpoint::Tcp(address) => {
+                let stream = TcpStream::connect(address)
+                    .with_context(|| format!("Failed to connect to remote signer {}", self))?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                send_request(stream, address, method, path, body)
+            }
+            #[cfg(unix)]
+            RemoteSignerEndpoint::Unix(socket_path) => {
+                let stream = UnixStream::connect(socket_path)
+                    .with_context(|| format!("Failed to connect to remote signer {}", self))?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                send_request(stream, "localhost", method, path, body)
+            }
+        }
+    }
+}
+
+impl FromStr for RemoteSignerEndpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(address) = s.strip_prefix("http://") {
+            return Ok(RemoteSignerEndpoint::Tcp(
+                address.trim_end_matches('/').to_string(),
+            ));
+        }
+        #[cfg(unix)]
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(RemoteSignerEndpoint::Unix(PathBuf::from(path)));
+        }
+        bail!("Invalid remote signer endpoint {}", s)
+    }
+}
+
+impl std::fmt::Display for RemoteSignerEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RemoteSignerEndpoint::Tcp(address) => write!(f, "http://{}", address),
+            #[cfg(unix)]
+            RemoteSignerEndpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Signer delegating to a signing service, so that the key never enters
+/// this process. The service speaks a minimal HTTP/1.1 protocol:
+///
+/// - `GET /verifying_key` returns the JSON encoded [`VerifyingKey`].
+/// - `POST /sign` with `{"message": "<base64>"}` returns
+///   `{"signature": <signature>}`.
+///
+/// [`SignerServer`] serves this protocol for any [`Signer`], signing only the
+/// [`FinalizedEpoch`]s of a single chain.
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    endpoint: RemoteSignerEndpoint,
+    verifying_key: VerifyingKey,
+}
+
+impl RemoteSigner {
+    /// Connects to the signer at the endpoint, e.g. `http://127.0.0.1:7000`
+    /// or `unix:/run/prism/signer.sock`, and fetches its verifying key.
+    pub fn connect(endpoint: &str) -> Result<Self> {
+        let endpoint = RemoteSignerEndpoint::from_str(endpoint)?;
+        let response = endpoint.request("GET", VERIFYING_KEY_PATH, &[])?;
+        let verifying_key = parse_json(&response)?;
+        Ok(RemoteSigner {
+            endpoint,
+            verifying_key,
+        })
+    }
+
+    pub fn endpoint(&self) -> &RemoteSignerEndpoint {
+        &self.endpoint
+    }
+}
+
+impl Signer for RemoteSigner {
+    /// Requests a signature from the remote signer. Signatures that do not
+    /// verify with the key fetched on connection are rejected.
+    fn sign(&self, message: &[u8]) -> Result<Signature> {
+        let request = serde_json::to_vec(&SignRequest {
+            message: message.to_vec(),
+        })?;
+        let response = self.endpoint.request("POST", SIGN_PATH, &request)?;
+        let SignResponse { signature } = parse_json(&response)?;
+
+        self.verifying_key
+            .verify_signature(message, &signature)
+            .context("Remote signer returned an invalid signature")?;
+        Ok(signature)
+    }
+
+    fn verifying_key(&self) -> VerifyingKey {
+        self.verifying_key.clone()
+    }
+}
+
+/// Serves the protocol of [`RemoteSigner`] for a signer, e.g. to keep the
+/// prover's key in a separate process. Requests are not authenticated, so the
+/// server only accepts local connections and refuses to sign anything but
+/// unsigned epochs of its chain. Connections are handled one at a time.
+pub struct SignerServer<S: Signer> {
+    signer: S,
+    chain_id: String,
+}
+
+impl<S: Signer> SignerServer<S> {
+    /// Creates a server signing the epochs of the chain with the signer.
+    pub fn new(signer: S, chain_id: &str) -> Self {
+        SignerServer {
+            signer,
+            chain_id: chain_id.to_string(),
+        }
+    }
+
+    /// Serves connections to the listener until accepting fails. The listener
+    /// has to be bound to a loopback address.
+    pub fn serve_tcp(&self, listener: TcpListener) -> Result<()> {
+        let address = listener.local_addr()?;
+        if !address.ip().is_loopback() {
+            bail!(
+                "Signer server must listen on a loopback address, not {}",
+                address
+            );
+        }
+
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+            // Failures only affect the client of the connection
+            let _ = self.handle_connection(stream);
+        }
+        Ok(())
+    }
+
+    /// Serves connections to the listener until accepting fails.
+    #[cfg(unix)]
+    pub fn serve_unix(&self, listener: UnixListener) -> Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+            let _ = self.handle_connection(stream);
+        }
+        Ok(())
+    }
+
+    /// Answers a single request on the stream.
+    pub fn handle_connection(&self, mut stream: impl Read + Write) -> Result<()> {
+        let (request_line, body) = read_message(&mut stream)?;
+        let mut parts = request_line.split(' ');
+        let (status, response) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some(VERIFYING_KEY_PATH)) => {
+                (200, serde_json::to_vec(&self.signer.verifying_key())?)
+            }
+            (Some("POST"), Some(SIGN_PATH)) => self.sign(&body),
+            _ => (404, b"Not found".to_vec()),
+        };
+        write_message(
+            &mut stream,
+            &format!("HTTP/1.1 {} {}", status, reason_phrase(status)),
+            &response,
+        )
+    }
+
+    fn sign(&self, body: &[u8]) -> (u16, Vec<u8>) {
+        let request: SignRequest = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(e) => return (400, format!("Invalid sign request: {}", e).into_bytes()),
+        };
+        if let Err(e) = self.validate_message(&request.message) {
+            return (403, format!("Refusing to sign: {}", e).into_bytes());
+        }
+        let signature = match self.signer.sign(&request.message) {
+            Ok(signature) => signature,
+            Err(e) => return (500, format!("Failed to sign: {}", e).into_bytes()),
+        };
+        match serde_json::to_vec(&SignResponse { signature }) {
+            Ok(response) => (200, response),
+            Err(e) => (
+                500,
+                format!("Failed to encode signature: {}", e).into_bytes(),
+            ),
+        }
+    }
+
+    /// Checks that the message is the signing payload of an unsigned epoch of
+    /// the server's chain, see [`FinalizedEpoch::insert_signature`].
+    fn validate_message(&self, message: &[u8]) -> Result<()> {
+        let (chain_id, epoch) = <(String, FinalizedEpoch)>::decode_from_bytes(message)
+            .map_err(|_| anyhow!("message is not an epoch"))?;
+        if chain_id != self.chain_id {
+            bail!("epoch belongs to chain {}", chain_id);
+        }
+        if epoch.signature.is_some() {
+            bail!("epoch is already signed");
+        }
+        // Rejects trailing bytes, which the decoding ignores
+        if (chain_id, epoch).encode_to_bytes()? != message {
+            bail!("message is not an epoch");
+        }
+        Ok(())
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        _ => "Internal Server Error",
+    }
+}
+
+fn parse_json<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes).map_err(|e| anyhow!("Invalid response of remote signer: {}", e))
+}
+
+fn send_request(
+    mut stream: impl Read + Write,
+    host: &str,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> Result<Vec<u8>> {
+    write_message(
+        &mut stream,
+        &format!("{} {} HTTP/1.1\r\nHost: {}", method, path, host),
+        body,
+    )?;
+    let (status_line, response) = read_message(&mut stream)?;
+
+    let status = status_line
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| anyhow!("Invalid HTTP status line: {}", status_line))?;
+    if status != 200 {
+        bail!(
+            "Remote signer responded with status {}: {}",
+            status,
+            String::from_utf8_lossy(&response)
+        );
+    }
+    Ok(response)
+}
+
+/// Writes an HTTP message with the start line (and preceding headers) and a
+/// JSON body, closing the connection afterwards.
+fn write_message(stream: &mut impl Write, head: &str, body: &[u8]) -> Result<()> {
+    write!(
+        stream,
+        "{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        head,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Reads an HTTP message, returning its start line and body.
+fn read_message(stream: &mut impl Read) -> Result<(String, Vec<u8>)> {
+    let mut reader = BufReader::new(stream.take(MAX_MESSAGE_LENGTH));
+
+    let mut start_line = String::new();
+    reader.read_line(&mut start_line)?;
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            bail!("Unexpected end of HTTP message");
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse().context("Invalid Content-Length header")?;
+            }
+        }
+    }
+    if content_length as u64 > MAX_MESSAGE_LENGTH {
+        bail!("HTTP message is too large");
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+    Ok((start_line.trim_end().to_string(), body))
+}
+
+#[cfg(test)]
+mod tests;
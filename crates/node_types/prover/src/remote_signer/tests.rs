@@ -0,0 +1,96 @@
+use prism_common::digest::Digest;
+use prism_da::FinalizedEpoch;
+use prism_keys::{Signer, SigningKey};
+use prism_serde::binary::ToBinary;
+use sp1_sdk::{SP1Proof, SP1ProofWithPublicValues, SP1PublicValues};
+use std::net::TcpListener;
+
+use super::{RemoteSigner, SignerServer};
+
+const CHAIN_ID: &str = "testnet";
+
+fn unsigned_epoch(public_values: &[u8]) -> FinalizedEpoch {
+    FinalizedEpoch {
+        height: 1,
+        prev_commitment: Digest::zero(),
+        current_commitment: Digest::hash(b"commitment"),
+        proof: SP1ProofWithPublicValues {
+            proof: SP1Proof::Core(vec![]),
+            public_values: SP1PublicValues::from(public_values),
+            sp1_version: String::new(),
+        },
+        signature: None,
+    }
+}
+
+#[test]
+fn test_remote_signer_over_tcp() {
+    let signing_key = SigningKey::new_ed25519();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let server = SignerServer::new(signing_key.clone(), CHAIN_ID);
+    std::thread::spawn(move || server.serve_tcp(listener));
+
+    let signer = RemoteSigner::connect(&endpoint).unwrap();
+    assert_eq!(signer.endpoint().to_string(), endpoint);
+    assert_eq!(signer.verifying_key(), signing_key.verifying_key());
+
+    let mut epoch = unsigned_epoch(b"public values");
+    epoch.insert_signature(&signer, CHAIN_ID).unwrap();
+    assert!(epoch.verify_signature(signing_key.verifying_key(), CHAIN_ID).is_ok());
+    assert!(RemoteSigner::connect("ftp://127.0.0.1:21").is_err());
+}
+
+#[test]
+fn test_signer_server_only_signs_unsigned_epochs_of_its_chain() {
+    let signing_key = SigningKey::new_ed25519();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let server = SignerServer::new(signing_key, CHAIN_ID);
+    std::thread::spawn(move || server.serve_tcp(listener));
+    let signer = RemoteSigner::connect(&endpoint).unwrap();
+
+    // Arbitrary messages
+    assert!(signer.sign(b"message").is_err());
+    assert!(signer.sign(&[]).is_err());
+
+    // Epochs of other chains
+    let mut epoch = unsigned_epoch(b"public values");
+    assert!(epoch.insert_signature(&signer, "mainnet").is_err());
+
+    // Epochs with trailing data
+    let mut message = (CHAIN_ID, epoch.clone()).encode_to_bytes().unwrap();
+    message.extend_from_slice(b"trailing");
+    assert!(signer.sign(&message).is_err());
+
+    // Epochs which are already signed
+    epoch.insert_signature(&signer, CHAIN_ID).unwrap();
+    let message = (CHAIN_ID, epoch).encode_to_bytes().unwrap();
+    assert!(signer.sign(&message).is_err());
+}
+
+#[test]
+fn test_signer_server_rejects_non_loopback_listeners() {
+    let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+    let server = SignerServer::new(SigningKey::new_ed25519(), CHAIN_ID);
+    assert!(server.serve_tcp(listener).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_remote_signer_over_unix_socket() {
+    let signing_key = SigningKey::new_ml_dsa_65();
+    let path = std::env::temp_dir().join(format!("prism-signer-{}.sock", std::process::id()));
+    let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let server = SignerServer::new(signing_key.clone(), CHAIN_ID);
+    std::thread::spawn(move || server.serve_unix(listener));
+
+    let signer = RemoteSigner::connect(&format!("unix:{}", path.display())).unwrap();
+    assert_eq!(signer.verifying_key(), signing_key.verifying_key());
+
+    // Signatures of large keys and epochs fit into single messages
+    let mut epoch = unsigned_epoch(&[7u8; 100_000]);
+    epoch.insert_signature(&signer, CHAIN_ID).unwrap();
+    assert!(epoch.verify_signature(signing_key.verifying_key(), CHAIN_ID).is_ok());
+    std::fs::remove_file(&path).unwrap();
+}
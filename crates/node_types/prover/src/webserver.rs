@@ -2,6 +2,7 @@ use crate::Prover;
 use anyhow::{bail, Context, Result};
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use prism_common::{account::Account, digest::Digest, transaction::Transaction};
+use prism_keys::{Fingerprint, SafetyNumber, VerifyingKey};
 use prism_tree::{proofs::HashedMerkleProof, AccountResponse as TreeAccountResponse};
 use serde::{Deserialize, Serialize};
 use std::{net::SocketAddr, sync::Arc};
@@ -43,15 +44,85 @@ pub struct AccountRequest {
     pub id: String,
 }
 
+#[derive(Serialize, Deserialize, ToSchema)]
+/// Request to retrieve account information as of a past epoch
+pub struct AccountAtRequest {
+    /// Identifier for the account to look up
+    pub id: String,
+    /// Epoch at whose start the account is looked up
+    #[schema(example = 42)]
+    pub epoch: u64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+/// Status of an account lookup
+pub enum AccountStatus {
+    /// The account exists and can be used
+    Active,
+    /// The account existed, but has been deactivated. Its id cannot be reused.
+    Deactivated,
+    /// No account exists for the id
+    NotFound,
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 /// Response containing account data and a corresponding Merkle proof
 pub struct AccountResponse {
+    /// Whether the account is active, deactivated or does not exist
+    pub status: AccountStatus,
     /// The account if found, or None if not found
     pub account: Option<Account>,
+    /// Keys of the account that have expired as of the current epoch and can
+    /// no longer be used to sign transactions
+    pub expired_keys: Vec<VerifyingKey>,
     /// Merkle proof for account membership or non-membership
     pub proof: HashedMerkleProof,
 }
 
+#[derive(Serialize, Deserialize, ToSchema)]
+/// Fingerprint of an account key, for comparison out of band
+pub struct KeyFingerprint {
+    /// The verifying key
+    pub key: VerifyingKey,
+    /// Algorithm-tagged digest of the key
+    #[schema(
+        value_type = String,
+        example = "ed25519:b2929c110ef922ca45e8e93f61965c7d15728696326995e5757bb0f83d44cbd8"
+    )]
+    pub fingerprint: Fingerprint,
+    /// Short form of the fingerprint for reading aloud
+    #[schema(example = "b292 9c11 0ef9 22ca 45e8")]
+    pub short: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+/// Response containing the fingerprints of the active keys of an account
+pub struct KeyFingerprintsResponse {
+    /// Epoch at which the keys are active
+    #[schema(example = 42)]
+    pub epoch: u64,
+    pub fingerprints: Vec<KeyFingerprint>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+/// Request to compute the safety number of two accounts
+pub struct SafetyNumberRequest {
+    /// Identifier of the first account
+    pub id: String,
+    /// Identifier of the second account
+    pub other_id: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+/// Response containing the safety number of two accounts
+pub struct SafetyNumberResponse {
+    /// Epoch at whose active keys the safety number is computed
+    #[schema(example = 42)]
+    pub epoch: u64,
+    /// Sixty digits in groups of five, identical for both accounts
+    pub safety_number: SafetyNumber,
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 /// Response representing a cryptographic commitment towards the current state of prism
 pub struct CommitmentResponse {
@@ -74,6 +145,9 @@ impl WebServer {
 
         let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
             .routes(routes!(get_account))
+            .routes(routes!(get_account_at))
+            .routes(routes!(get_key_fingerprints))
+            .routes(routes!(get_safety_number))
             .routes(routes!(post_transaction))
             .routes(routes!(get_commitment))
             .layer(CorsLayer::permissive())
@@ -163,26 +237,180 @@ async fn get_account(
             .into_response();
     };
 
-    match account_response {
-        TreeAccountResponse::Found(account, membership_proof) => (
+    let get_epoch_result = session.db.get_epoch();
+    let Ok(epoch) = get_epoch_result else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!(
+                "Failed to retrieve current epoch: {}",
+                get_epoch_result.unwrap_err()
+            ),
+        )
+            .into_response();
+    };
+
+    (
+        StatusCode::OK,
+        Json(to_account_response(account_response, epoch)),
+    )
+        .into_response()
+}
+
+/// The /get-account-at endpoint returns an account as it was at the start of
+/// a past epoch.
+///
+/// The proof is against the commitment of that epoch. If no commitment is
+/// known for the epoch, the endpoint will return a 400 response.
+///
+#[utoipa::path(
+    post,
+    path = "/get-account-at",
+    request_body = AccountAtRequest,
+    responses(
+        (status = 200, description = "Successfully retrieved historical account", body = AccountResponse),
+        (status = 400, description = "Bad request")
+    )
+)]
+async fn get_account_at(
+    State(session): State<Arc<Prover>>,
+    Json(request): Json<AccountAtRequest>,
+) -> impl IntoResponse {
+    match session.get_account_at(&request.id, request.epoch).await {
+        Ok(account_response) => (
             StatusCode::OK,
-            Json(AccountResponse {
-                account: Some(*account),
-                proof: membership_proof.hashed(),
-            }),
+            Json(to_account_response(account_response, request.epoch)),
         )
             .into_response(),
-        TreeAccountResponse::NotFound(non_membership_proof) => (
-            StatusCode::OK,
-            Json(AccountResponse {
-                account: None,
-                proof: non_membership_proof.hashed(),
-            }),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Failed to retrieve account at epoch {}: {}",
+                request.epoch, e
+            ),
         )
             .into_response(),
     }
 }
 
+/// The /get-key-fingerprints endpoint returns the fingerprints of the keys
+/// of an account that are active in the current epoch.
+///
+/// Users compare fingerprints out of band to make sure that they see the same
+/// keys. If the account is not active, the endpoint will return a 400 response.
+///
+#[utoipa::path(
+    post,
+    path = "/get-key-fingerprints",
+    request_body = AccountRequest,
+    responses(
+        (status = 200, description = "Successfully computed key fingerprints", body = KeyFingerprintsResponse),
+        (status = 400, description = "Bad request")
+    )
+)]
+async fn get_key_fingerprints(
+    State(session): State<Arc<Prover>>,
+    Json(request): Json<AccountRequest>,
+) -> impl IntoResponse {
+    let result = async {
+        let epoch = session.db.get_epoch()?;
+        let account = get_active_account(&session, &request.id).await?;
+        let fingerprints = account
+            .active_keys(epoch)
+            .into_iter()
+            .map(|key| KeyFingerprint {
+                key: key.clone(),
+                fingerprint: key.fingerprint(),
+                short: key.fingerprint().to_short_string(),
+            })
+            .collect();
+        anyhow::Ok(KeyFingerprintsResponse {
+            epoch,
+            fingerprints,
+        })
+    };
+
+    match result.await {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            format!("Failed to compute key fingerprints: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// The /get-safety-number endpoint returns the safety number of two accounts,
+/// computed from their keys that are active in the current epoch.
+///
+/// Both account holders compare the safety number out of band, e.g. in
+/// person. If either account is not active, the endpoint will return a 400
+/// response.
+///
+#[utoipa::path(
+    post,
+    path = "/get-safety-number",
+    request_body = SafetyNumberRequest,
+    responses(
+        (status = 200, description = "Successfully computed safety number", body = SafetyNumberResponse),
+        (status = 400, description = "Bad request")
+    )
+)]
+async fn get_safety_number(
+    State(session): State<Arc<Prover>>,
+    Json(request): Json<SafetyNumberRequest>,
+) -> impl IntoResponse {
+    let result = async {
+        let epoch = session.db.get_epoch()?;
+        let account = get_active_account(&session, &request.id).await?;
+        let other_account = get_active_account(&session, &request.other_id).await?;
+        let safety_number = account.safety_number(&other_account, epoch)?;
+        anyhow::Ok(SafetyNumberResponse {
+            epoch,
+            safety_number,
+        })
+    };
+
+    match result.await {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            format!("Failed to compute safety number: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+async fn get_active_account(session: &Prover, id: &str) -> Result<Account> {
+    match session.get_account(id).await? {
+        TreeAccountResponse::Found(account, _) => Ok(*account),
+        TreeAccountResponse::Deactivated(..) => bail!("Account {} is deactivated", id),
+        TreeAccountResponse::NotFound(_) => bail!("Account {} not found", id),
+    }
+}
+
+fn to_account_response(account_response: TreeAccountResponse, epoch: u64) -> AccountResponse {
+    match account_response {
+        TreeAccountResponse::Found(account, membership_proof) => AccountResponse {
+            status: AccountStatus::Active,
+            expired_keys: account.expired_keys(epoch).into_iter().cloned().collect(),
+            account: Some(*account),
+            proof: membership_proof.hashed(),
+        },
+        TreeAccountResponse::Deactivated(account, membership_proof) => AccountResponse {
+            status: AccountStatus::Deactivated,
+            account: Some(*account),
+            expired_keys: vec![],
+            proof: membership_proof.hashed(),
+        },
+        TreeAccountResponse::NotFound(non_membership_proof) => AccountResponse {
+            status: AccountStatus::NotFound,
+            account: None,
+            expired_keys: vec![],
+            proof: non_membership_proof.hashed(),
+        },
+    }
+}
+
 /// Returns the commitment (tree root) of the IndexedMerkleTree initialized from the database.
 ///
 #[utoipa::path(
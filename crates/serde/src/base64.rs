@@ -1,6 +1,7 @@
 use base64::{
-    decoded_len_estimate, engine::general_purpose::STANDARD as BASE64, DecodeError,
-    DecodeSliceError, Engine,
+    decoded_len_estimate,
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64_URL},
+    DecodeError, DecodeSliceError, Engine,
 };
 
 pub trait ToBase64 {
@@ -46,3 +47,31 @@ impl FromBase64 for [u8; 32] {
         Ok(output)
     }
 }
+
+/// Unpadded base64url encoding (RFC 4648, section 5), as used by JOSE.
+pub trait ToBase64Url {
+    fn to_base64_url(&self) -> String;
+}
+
+impl<T> ToBase64Url for T
+where
+    T: AsRef<[u8]>,
+{
+    fn to_base64_url(&self) -> String {
+        BASE64_URL.encode(self)
+    }
+}
+
+pub trait FromBase64Url: Sized {
+    type Error;
+
+    fn from_base64_url<T: AsRef<[u8]>>(base64_url: T) -> Result<Self, Self::Error>;
+}
+
+impl FromBase64Url for Vec<u8> {
+    type Error = DecodeError;
+
+    fn from_base64_url<T: AsRef<[u8]>>(base64_url: T) -> Result<Self, Self::Error> {
+        BASE64_URL.decode(base64_url)
+    }
+}
@@ -17,6 +17,10 @@ pub trait Database: Send + Sync + TreeReader + TreeWriter {
     fn get_commitment(&self, epoch: &u64) -> Result<Digest>;
     fn set_commitment(&self, epoch: &u64, commitment: &Digest) -> Result<()>;
 
+    /// The version of the state tree whose root is the commitment of the epoch.
+    fn get_tree_version(&self, epoch: &u64) -> Result<u64>;
+    fn set_tree_version(&self, epoch: &u64, version: &u64) -> Result<()>;
+
     fn get_epoch(&self) -> Result<u64>;
     fn set_epoch(&self, epoch: &u64) -> Result<()>;
 
@@ -16,6 +16,7 @@ pub struct InMemoryDatabase {
     nodes: Arc<Mutex<HashMap<NodeKey, Node>>>,
     values: Arc<Mutex<HashMap<(Version, KeyHash), OwnedValue>>>,
     commitments: Arc<Mutex<HashMap<u64, Digest>>>,
+    tree_versions: Arc<Mutex<HashMap<u64, u64>>>,
     current_epoch: Arc<Mutex<u64>>,
     sync_height: Arc<Mutex<u64>>,
 }
@@ -26,6 +27,7 @@ impl InMemoryDatabase {
             nodes: Arc::new(Mutex::new(HashMap::new())),
             values: Arc::new(Mutex::new(HashMap::new())),
             commitments: Arc::new(Mutex::new(HashMap::new())),
+            tree_versions: Arc::new(Mutex::new(HashMap::new())),
             current_epoch: Arc::new(Mutex::new(0)),
             sync_height: Arc::new(Mutex::new(1)),
         }
@@ -102,6 +104,17 @@ impl Database for InMemoryDatabase {
         Ok(())
     }
 
+    fn get_tree_version(&self, epoch: &u64) -> Result<u64> {
+        self.tree_versions.lock().unwrap().get(epoch).copied().ok_or_else(|| {
+            DatabaseError::NotFoundError(format!("tree version from epoch_{}", epoch)).into()
+        })
+    }
+
+    fn set_tree_version(&self, epoch: &u64, version: &u64) -> Result<()> {
+        self.tree_versions.lock().unwrap().insert(*epoch, *version);
+        Ok(())
+    }
+
     fn get_epoch(&self) -> Result<u64> {
         Ok(*self.current_epoch.lock().unwrap())
     }
@@ -124,6 +137,7 @@ impl Database for InMemoryDatabase {
         self.nodes.lock().unwrap().clear();
         self.values.lock().unwrap().clear();
         self.commitments.lock().unwrap().clear();
+        self.tree_versions.lock().unwrap().clear();
         *self.current_epoch.lock().unwrap() = 0;
         Ok(())
     }
@@ -207,6 +207,35 @@ impl Database for RedisConnection {
             })
     }
 
+    fn get_tree_version(&self, epoch: &u64) -> Result<u64> {
+        let mut con = self.lock_connection()?;
+        let version: Option<u64> =
+            con.get(format!("tree_versions:epoch_{}", epoch)).map_err(|_| {
+                anyhow!(DatabaseError::ReadError(format!(
+                    "tree version from epoch_{}",
+                    epoch
+                )))
+            })?;
+        version.ok_or_else(|| {
+            anyhow!(DatabaseError::NotFoundError(format!(
+                "tree version from epoch_{}",
+                epoch
+            )))
+        })
+    }
+
+    fn set_tree_version(&self, epoch: &u64, version: &u64) -> Result<()> {
+        let mut con = self.lock_connection()?;
+        con.set::<&String, &u64, ()>(&format!("tree_versions:epoch_{}", epoch), version).map_err(
+            |_| {
+                anyhow!(DatabaseError::WriteError(format!(
+                    "tree version for epoch: {}",
+                    epoch
+                )))
+            },
+        )
+    }
+
     fn flush_database(&self) -> Result<()> {
         let mut conn = self.lock_connection()?;
         redis::cmd("FLUSHALL")
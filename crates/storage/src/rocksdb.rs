@@ -16,6 +16,7 @@ use rocksdb::{DBWithThreadMode, MultiThreaded, Options, DB};
 use serde::{Deserialize, Serialize};
 
 const KEY_PREFIX_COMMITMENTS: &str = "commitments:epoch_";
+const KEY_PREFIX_TREE_VERSIONS: &str = "tree_versions:epoch_";
 const KEY_PREFIX_NODE: &str = "node:";
 const KEY_PREFIX_VALUE_HISTORY: &str = "value_history:";
 
@@ -72,6 +73,24 @@ impl Database for RocksDBConnection {
         )?)
     }
 
+    fn get_tree_version(&self, epoch: &u64) -> anyhow::Result<u64> {
+        let key = format!("{KEY_PREFIX_TREE_VERSIONS}{}", epoch);
+        let res = self.connection.get(key.as_bytes())?.ok_or_else(|| {
+            DatabaseError::NotFoundError(format!("tree version from epoch_{}", epoch))
+        })?;
+
+        Ok(u64::from_be_bytes(res.try_into().map_err(|e| {
+            anyhow!("failed byte conversion from BigEndian to u64: {:?}", e)
+        })?))
+    }
+
+    fn set_tree_version(&self, epoch: &u64, version: &u64) -> anyhow::Result<()> {
+        Ok(self.connection.put(
+            format!("{KEY_PREFIX_TREE_VERSIONS}{}", epoch).as_bytes(),
+            version.to_be_bytes(),
+        )?)
+    }
+
     fn get_last_synced_height(&self) -> anyhow::Result<u64> {
         let res = self
             .connection
@@ -213,6 +232,20 @@ mod tests {
         assert_eq!(read_commitment, commitment);
     }
 
+    #[test]
+    fn test_rw_tree_version() {
+        let (_temp_dir, db) = setup_db();
+
+        let epoch = 1;
+        let version = 42;
+
+        db.set_tree_version(&epoch, &version).unwrap();
+        let read_version = db.get_tree_version(&epoch).unwrap();
+
+        assert_eq!(read_version, version);
+        assert!(db.get_tree_version(&2).is_err());
+    }
+
     #[test]
     fn test_rw_epoch() {
         let (_temp_dir, db) = setup_db();
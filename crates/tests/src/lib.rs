@@ -65,7 +65,7 @@ async fn test_light_client_prover_talking() -> Result<()> {
     let pubkey = signing_key.verifying_key();
 
     let prover_cfg = prism_prover::Config {
-        signing_key,
+        signer: Arc::new(signing_key),
         ..prism_prover::Config::default()
     };
 
@@ -80,6 +80,7 @@ async fn test_light_client_prover_talking() -> Result<()> {
         lc_cfg.start_height,
         Some(pubkey),
         vk.bytes32(),
+        prover_cfg.params.clone(),
     ));
 
     let prover_clone = prover.clone();
@@ -4,7 +4,7 @@ use jmt::{
     storage::{NodeBatch, TreeReader, TreeUpdateBatch, TreeWriter},
     JellyfishMerkleTree, KeyHash, RootHash,
 };
-use prism_common::digest::Digest;
+use prism_common::{digest::Digest, params::ProtocolParams};
 use std::sync::Arc;
 
 use crate::hasher::TreeHasher;
@@ -19,6 +19,8 @@ where
 {
     pub(crate) jmt: JellyfishMerkleTree<Arc<S>, TreeHasher>,
     pub(crate) epoch: u64,
+    /// Network parameters that all transactions are validated against
+    pub(crate) params: ProtocolParams,
     pending_batch: Option<NodeBatch>,
     db: Arc<S>,
 }
@@ -28,11 +30,16 @@ where
     S: TreeReader + TreeWriter,
 {
     pub fn new(store: Arc<S>) -> Self {
+        Self::new_with_params(store, ProtocolParams::default())
+    }
+
+    pub fn new_with_params(store: Arc<S>, params: ProtocolParams) -> Self {
         let tree = Self {
             db: store.clone(),
             jmt: JellyfishMerkleTree::<Arc<S>, TreeHasher>::new(store),
             pending_batch: None,
             epoch: 0,
+            params,
         };
         let (_, batch) = tree
             .jmt
@@ -42,18 +49,25 @@ where
         tree
     }
 
-    pub fn load(store: Arc<S>, epoch: u64) -> Self {
+    pub fn load(store: Arc<S>, epoch: u64, params: ProtocolParams) -> Self {
         if epoch == 0 {
-            return KeyDirectoryTree::new(store);
+            return KeyDirectoryTree::new_with_params(store, params);
         }
         Self {
             db: store.clone(),
             jmt: JellyfishMerkleTree::<Arc<S>, TreeHasher>::new(store),
             pending_batch: None,
             epoch,
+            params,
         }
     }
 
+    /// Returns the current version of the underlying JMT. Every applied
+    /// transaction creates a new version.
+    pub fn version(&self) -> u64 {
+        self.epoch
+    }
+
     pub fn get_commitment(&self) -> Result<Digest> {
         let root = self.get_current_root()?;
         Ok(Digest(root.0))
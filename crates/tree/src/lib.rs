@@ -12,6 +12,9 @@ pub enum AccountResponse {
     /// When an account was found, provides the value and its corresponding membership-proof
     Found(Box<Account>, MerkleProof),
 
+    /// When the account was deactivated, provides the tombstoned value and its corresponding membership-proof
+    Deactivated(Box<Account>, MerkleProof),
+
     /// When no account was found for a specific key, provides the corresponding non-membership-proof
     NotFound(MerkleProof),
 }
@@ -8,7 +8,8 @@ use jmt::{
 use prism_common::{
     account::Account,
     digest::Digest,
-    operation::{Operation, ServiceChallenge, ServiceChallengeInput},
+    operation::{Operation, ServiceChallenge},
+    params::{ProtocolParams, ValidationContext},
     transaction::Transaction,
 };
 use prism_serde::binary::ToBinary;
@@ -24,62 +25,112 @@ pub struct Batch {
     pub prev_root: Digest,
     pub new_root: Digest,
 
+    /// The epoch the batch is applied in. All transactions are validated against it.
+    pub epoch: u64,
+    /// The network parameters all transactions are validated against.
+    pub params: ProtocolParams,
+
     pub service_proofs: HashMap<String, ServiceProof>,
     pub proofs: Vec<Proof>,
 }
 
 impl Batch {
-    pub fn init(prev_root: Digest, next_root: Digest, proofs: Vec<Proof>) -> Self {
+    pub fn init(
+        prev_root: Digest,
+        next_root: Digest,
+        epoch: u64,
+        params: ProtocolParams,
+        proofs: Vec<Proof>,
+    ) -> Self {
         Batch {
             prev_root,
             new_root: next_root,
+            epoch,
+            params,
             service_proofs: HashMap::new(),
             proofs,
         }
     }
 
+    /// Verifies all proofs of the batch in order. Signatures are collected
+    /// along the way and verified at once at the end.
     pub fn verify(&self) -> Result<()> {
+        let ctx = ValidationContext::new(self.epoch, self.params.clone()).with_batch_verification();
         let mut root = self.prev_root;
+        // State of the services used in the batch as of the current proof.
+        let mut services: HashMap<String, Account> = HashMap::new();
+
         for proof in &self.proofs {
             match proof {
                 Proof::Insert(insert_proof) => {
                     // When verifying account creation, ensure service challenge is verified as well
-                    let challenge = match &insert_proof.tx.operation {
-                        Operation::CreateAccount { service_id, .. } => {
-                            let service_challenge = self
-                                .service_proofs
-                                .get(service_id)
-                                .and_then(|service_proof| service_proof.service_challenge());
-                            if service_challenge.is_none() {
-                                bail!("Service proof for {} is missing from batch for CreateAccount verification", service_id);
-                            }
-                            service_challenge
+                    let challenge = match insert_proof.tx.operations.first() {
+                        Some(Operation::CreateAccount { service_id, .. }) => {
+                            self.service_at(&mut services, service_id, root)?.service_challenge()
                         }
 
                         _ => None,
                     };
-                    insert_proof.verify(challenge)?;
+                    let account = insert_proof.verify(challenge, &ctx)?;
+
+                    if let Some(Operation::RegisterService { id, .. }) =
+                        insert_proof.tx.operations.first()
+                    {
+                        services.insert(id.clone(), account);
+                    }
                     root = insert_proof.new_root;
                 }
                 Proof::Update(update_proof) => {
-                    update_proof.verify()?;
+                    // Delegated transactions are authorized by the service's keys
+                    let delegate = if update_proof.tx.delegated {
+                        let Some(service_id) = update_proof.old_account.delegate() else {
+                            bail!(
+                                "Account {} has not delegated to a service",
+                                update_proof.tx.id
+                            );
+                        };
+                        Some(self.service_at(&mut services, service_id, root)?.clone())
+                    } else {
+                        None
+                    };
+                    let account = update_proof.verify(delegate.as_ref(), &ctx)?;
+
+                    // Later account creations have to meet the updated challenge
+                    if let Some(service) = services.get_mut(&update_proof.tx.id) {
+                        *service = account;
+                    }
                     root = update_proof.new_root;
                 }
             }
         }
 
         assert_eq!(root, self.new_root);
-        for (id, service_proof) in &self.service_proofs {
-            let keyhash = KeyHash::with::<TreeHasher>(&id);
-            let serialized_account = service_proof.service.encode_to_bytes()?;
-            service_proof.proof.verify_existence(RootHash(root.0), keyhash, serialized_account)?;
-        }
 
-        Ok(())
+        ctx.verify_batch()
+    }
+
+    /// Returns the state of the service at the given root, verifying its
+    /// service proof when it is used for the first time in the batch.
+    fn service_at<'a>(
+        &self,
+        services: &'a mut HashMap<String, Account>,
+        service_id: &str,
+        root: Digest,
+    ) -> Result<&'a Account> {
+        if !services.contains_key(service_id) {
+            let Some(service_proof) = self.service_proofs.get(service_id) else {
+                bail!("Service proof for {} is missing from batch", service_id);
+            };
+            service_proof.verify(service_id, root)?;
+            services.insert(service_id.to_string(), service_proof.service.clone());
+        }
+        Ok(&services[service_id])
     }
 }
 
 #[derive(Serialize, Deserialize)]
+/// Proves the state of a service at the point in a batch where an account is
+/// first created for it.
 pub struct ServiceProof {
     pub root: Digest,
     pub proof: SparseMerkleProof<TreeHasher>,
@@ -90,6 +141,22 @@ impl ServiceProof {
     pub fn service_challenge(&self) -> Option<&ServiceChallenge> {
         self.service.service_challenge()
     }
+
+    /// Verifies that [`ServiceProof::service`] is the state of the service
+    /// with the given id at the given root.
+    pub fn verify(&self, service_id: &str, root: Digest) -> Result<()> {
+        if self.root != root {
+            bail!(
+                "Service proof for {} was created for a different root",
+                service_id
+            );
+        }
+
+        let keyhash = KeyHash::with::<TreeHasher>(service_id);
+        let serialized_account = self.service.encode_to_bytes()?;
+        self.proof.verify_existence(RootHash(root.0), keyhash, serialized_account)?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -120,29 +187,31 @@ pub struct InsertProof {
 
 impl InsertProof {
     /// The method called in circuit to verify the state transition to the new root.
-    pub fn verify(&self, service_challenge: Option<&ServiceChallenge>) -> Result<()> {
+    /// Returns the inserted account.
+    pub fn verify(
+        &self,
+        service_challenge: Option<&ServiceChallenge>,
+        ctx: &ValidationContext,
+    ) -> Result<Account> {
         self.non_membership_proof.verify_nonexistence().context("Invalid NonMembershipProof")?;
 
         let mut account = Account::default();
-        account.process_transaction(&self.tx)?;
+        account.process_transaction(&self.tx, ctx)?;
 
         // If we are creating an account, we need to additionally verify the service challenge
-        if let Operation::CreateAccount {
+        if let Some(Operation::CreateAccount {
             id,
             service_id,
             challenge,
             key,
-        } = &self.tx.operation
+            ..
+        }) = self.tx.operations.first()
         {
-            let hash = Digest::hash_items(&[id.as_bytes(), service_id.as_bytes(), &key.to_bytes()]);
-
-            if service_challenge.is_none() {
+            let Some(service_challenge) = service_challenge else {
                 bail!("Service challenge is missing for CreateAccount verification");
-            }
+            };
 
-            let ServiceChallenge::Signed(challenge_vk) = service_challenge.unwrap();
-            let ServiceChallengeInput::Signed(challenge_signature) = challenge;
-            challenge_vk.verify_signature(&hash.to_bytes(), challenge_signature)?;
+            service_challenge.verify_input(challenge, id, service_id, key, ctx)?;
         }
 
         let serialized_account = account.encode_to_bytes()?;
@@ -153,7 +222,7 @@ impl InsertProof {
             serialized_account,
         )?;
 
-        Ok(())
+        Ok(account)
     }
 }
 
@@ -176,7 +245,9 @@ pub struct UpdateProof {
 
 impl UpdateProof {
     /// The method called in circuit to verify the state transition to the new root.
-    pub fn verify(&self) -> Result<()> {
+    /// Delegated transactions need the state of the account's delegate service.
+    /// Returns the updated account.
+    pub fn verify(&self, delegate: Option<&Account>, ctx: &ValidationContext) -> Result<Account> {
         // Verify existence of old value.
         // Otherwise, any arbitrary account could be set as old_account.
         let old_serialized_account = self.old_account.encode_to_bytes()?;
@@ -187,7 +258,10 @@ impl UpdateProof {
         )?;
 
         let mut new_account = self.old_account.clone();
-        new_account.process_transaction(&self.tx)?;
+        match delegate {
+            Some(service) => new_account.process_delegated_transaction(&self.tx, service, ctx)?,
+            None => new_account.process_transaction(&self.tx, ctx)?,
+        }
 
         // Ensure the update proof corresponds to the new account value
         let new_serialized_account = new_account.encode_to_bytes()?;
@@ -197,7 +271,7 @@ impl UpdateProof {
             vec![(self.key, Some(new_serialized_account))],
         )?;
 
-        Ok(())
+        Ok(new_account)
     }
 }
 
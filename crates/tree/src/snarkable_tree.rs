@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::{bail, ensure, Result};
 use jmt::{
@@ -10,9 +10,7 @@ use prism_errors::DatabaseError;
 use prism_serde::binary::{FromBinary, ToBinary};
 
 use prism_common::{
-    account::Account,
-    digest::Digest,
-    operation::{Operation, ServiceChallenge, ServiceChallengeInput},
+    account::Account, digest::Digest, operation::Operation, params::ValidationContext,
     transaction::Transaction,
 };
 
@@ -27,28 +25,72 @@ use crate::{
 /// The methods of this trait are NOT run in circuit: they are used to create verifiable inputs for the circuit.
 /// This distinction is critical because the returned proofs must contain all information necessary to verify the operations.
 pub trait SnarkableTree: Send + Sync {
-    fn process_batch(&mut self, transactions: Vec<Transaction>) -> Result<Batch>;
-    fn process_transaction(&mut self, transaction: Transaction) -> Result<Proof>;
-    fn insert(&mut self, key: KeyHash, tx: Transaction) -> Result<InsertProof>;
-    fn update(&mut self, key: KeyHash, tx: Transaction) -> Result<UpdateProof>;
+    fn process_batch(&mut self, transactions: Vec<Transaction>, epoch: u64) -> Result<Batch>;
+    fn process_transaction(&mut self, transaction: Transaction, epoch: u64) -> Result<Proof>;
+    fn insert(&mut self, key: KeyHash, tx: Transaction, epoch: u64) -> Result<InsertProof>;
+    fn update(&mut self, key: KeyHash, tx: Transaction, epoch: u64) -> Result<UpdateProof>;
     fn get(&self, key: KeyHash) -> Result<AccountResponse>;
+    fn get_at(&self, key: KeyHash, version: u64) -> Result<AccountResponse>;
 }
 
 impl<S> SnarkableTree for KeyDirectoryTree<S>
 where
     S: TreeReader + TreeWriter + Send + Sync,
 {
-    fn process_batch(&mut self, transactions: Vec<Transaction>) -> Result<Batch> {
+    fn process_batch(&mut self, transactions: Vec<Transaction>, epoch: u64) -> Result<Batch> {
         debug!("creating block with {} transactions", transactions.len());
         let prev_commitment = self.get_commitment()?;
-        let mut services = HashSet::new();
+
+        // Services whose state the batch verifier can track from a service
+        // proof or from their registration within the batch
+        let mut known_services = HashSet::new();
+        let mut service_proofs = HashMap::new();
 
         let mut proofs = Vec::new();
         for transaction in transactions {
-            match self.process_transaction(transaction.clone()) {
+            // Account creations need the service's challenge, delegated
+            // transactions the keys of the service the account delegated to
+            let required_service = match transaction.operations.first() {
+                Some(Operation::CreateAccount { service_id, .. }) => Some(service_id.clone()),
+                _ if transaction.delegated => {
+                    match self.get(KeyHash::with::<TreeHasher>(&transaction.id))? {
+                        Found(account, _) => account.delegate().map(str::to_string),
+                        Deactivated(_, _) | NotFound(_) => None,
+                    }
+                }
+                _ => None,
+            };
+
+            // A service is proven as of the first transaction requiring it in
+            // the batch. Subsequent updates are tracked by the verifier.
+            let service_proof = match &required_service {
+                Some(service_id) if !known_services.contains(service_id) => {
+                    let service_key_hash = KeyHash::with::<TreeHasher>(service_id);
+                    match self.get(service_key_hash)? {
+                        Found(service, proof) => Some(ServiceProof {
+                            root: self.get_commitment()?,
+                            service: *service,
+                            proof: proof.proof,
+                        }),
+                        // Processing the transaction fails in these cases
+                        Deactivated(_, _) | NotFound(_) => None,
+                    }
+                }
+                _ => None,
+            };
+
+            match self.process_transaction(transaction.clone(), epoch) {
                 Ok(proof) => {
-                    if let Operation::CreateAccount { service_id, .. } = transaction.operation {
-                        services.insert(service_id);
+                    if let (Some(service_id), Some(service_proof)) =
+                        (required_service, service_proof)
+                    {
+                        known_services.insert(service_id.clone());
+                        service_proofs.insert(service_id, service_proof);
+                    }
+                    if let Some(Operation::RegisterService { id, .. }) =
+                        transaction.operations.first()
+                    {
+                        known_services.insert(id.clone());
                     }
                     proofs.push(proof)
                 }
@@ -64,47 +106,32 @@ where
 
         let current_commitment = self.get_commitment()?;
 
-        let mut batch = Batch::init(prev_commitment, current_commitment, proofs);
-
-        for service in services {
-            let service_key_hash = KeyHash::with::<TreeHasher>(&service);
-            let response = self.get(service_key_hash)?;
-            match response {
-                Found(account, proof) => {
-                    let service_proof = ServiceProof {
-                        root: current_commitment,
-                        service: *account,
-                        proof: proof.proof,
-                    };
-                    batch.service_proofs.insert(service.clone(), service_proof);
-                }
-                NotFound(proof) => {
-                    bail!("Service account not found: {:?}", proof);
-                }
-            }
-        }
+        let mut batch = Batch::init(
+            prev_commitment,
+            current_commitment,
+            epoch,
+            self.params.clone(),
+            proofs,
+        );
+        batch.service_proofs = service_proofs;
 
         Ok(batch)
     }
 
-    fn process_transaction(&mut self, transaction: Transaction) -> Result<Proof> {
-        match &transaction.operation {
-            Operation::AddKey { .. }
-            | Operation::RevokeKey { .. }
-            | Operation::AddData { .. }
-            | Operation::SetData { .. } => {
-                let key_hash = KeyHash::with::<TreeHasher>(&transaction.id);
-
-                debug!("updating account for user id {}", transaction.id);
-                let proof = self.update(key_hash, transaction)?;
+    fn process_transaction(&mut self, transaction: Transaction, epoch: u64) -> Result<Proof> {
+        // Only the first operation of a transaction can create an account, all
+        // other operations are applied to the existing or newly created account
+        let Some(first_operation) = transaction.operations.first() else {
+            bail!("Transaction contains no operations");
+        };
 
-                Ok(Proof::Update(Box::new(proof)))
-            }
+        match first_operation {
             Operation::CreateAccount {
                 id,
                 service_id,
                 challenge,
                 key,
+                ..
             } => {
                 ensure!(
                     transaction.id == id.as_str(),
@@ -114,7 +141,7 @@ where
                 let account_key_hash = KeyHash::with::<TreeHasher>(id);
 
                 // Verify that the account doesn't already exist
-                if matches!(self.get(account_key_hash)?, Found(_, _)) {
+                if !matches!(self.get(account_key_hash)?, NotFound(_)) {
                     bail!(DatabaseError::NotFoundError(format!(
                         "Account already exists for ID {}",
                         id
@@ -131,18 +158,12 @@ where
                     bail!("Service account does not contain a service challenge");
                 };
 
-                // Hash and sign credentials that have been signed by the external service
-                let hash =
-                    Digest::hash_items(&[id.as_bytes(), service_id.as_bytes(), &key.to_bytes()]);
-
-                let ServiceChallenge::Signed(service_pubkey) = service_challenge;
-                let ServiceChallengeInput::Signed(challenge_signature) = &challenge;
-
-                service_pubkey.verify_signature(&hash.to_bytes(), challenge_signature)?;
+                let ctx = ValidationContext::new(epoch, self.params.clone());
+                service_challenge.verify_input(challenge, id, service_id, key, &ctx)?;
 
                 debug!("creating new account for user ID {}", id);
 
-                let insert_proof = self.insert(account_key_hash, transaction)?;
+                let insert_proof = self.insert(account_key_hash, transaction, epoch)?;
                 Ok(Proof::Insert(Box::new(insert_proof)))
             }
             Operation::RegisterService { id, .. } => {
@@ -155,13 +176,27 @@ where
 
                 debug!("creating new account for service id {}", id);
 
-                let insert_proof = self.insert(key_hash, transaction)?;
+                let insert_proof = self.insert(key_hash, transaction, epoch)?;
                 Ok(Proof::Insert(Box::new(insert_proof)))
             }
+            _ => {
+                let key_hash = KeyHash::with::<TreeHasher>(&transaction.id);
+
+                debug!("updating account for user id {}", transaction.id);
+                let proof = self.update(key_hash, transaction, epoch)?;
+
+                Ok(Proof::Update(Box::new(proof)))
+            }
         }
     }
 
-    fn insert(&mut self, key: KeyHash, transaction: Transaction) -> Result<InsertProof> {
+    fn insert(
+        &mut self,
+        key: KeyHash,
+        transaction: Transaction,
+        epoch: u64,
+    ) -> Result<InsertProof> {
+        let ctx = ValidationContext::new(epoch, self.params.clone());
         let old_root = self.get_commitment()?;
         let (None, non_membership_merkle_proof) = self.jmt.get_with_proof(key, self.epoch)? else {
             bail!("Key already exists");
@@ -174,7 +209,7 @@ where
         };
 
         let mut account = Account::default();
-        account.process_transaction(&transaction)?;
+        account.process_transaction(&transaction, &ctx)?;
         let serialized_account = account.encode_to_bytes()?;
 
         // the update proof just contains another nm proof
@@ -194,7 +229,13 @@ where
         })
     }
 
-    fn update(&mut self, key: KeyHash, transaction: Transaction) -> Result<UpdateProof> {
+    fn update(
+        &mut self,
+        key: KeyHash,
+        transaction: Transaction,
+        epoch: u64,
+    ) -> Result<UpdateProof> {
+        let ctx = ValidationContext::new(epoch, self.params.clone());
         let old_root = self.get_current_root()?;
         let (Some(old_serialized_account), inclusion_proof) =
             self.jmt.get_with_proof(key, self.epoch)?
@@ -205,7 +246,17 @@ where
         let old_account = Account::decode_from_bytes(&old_serialized_account)?;
 
         let mut new_account = old_account.clone();
-        new_account.process_transaction(&transaction)?;
+        if transaction.delegated {
+            let Some(service_id) = old_account.delegate() else {
+                bail!("Account {} has not delegated to a service", transaction.id);
+            };
+            let Found(service, _) = self.get(KeyHash::with::<TreeHasher>(service_id))? else {
+                bail!("Failed to get account for service ID {}", service_id);
+            };
+            new_account.process_delegated_transaction(&transaction, &service, &ctx)?;
+        } else {
+            new_account.process_transaction(&transaction, &ctx)?;
+        }
 
         let serialized_value = new_account.encode_to_bytes()?;
 
@@ -228,13 +279,25 @@ where
     }
 
     fn get(&self, key: KeyHash) -> Result<AccountResponse> {
-        let root = self.get_commitment()?;
-        let (value, proof) = self.jmt.get_with_proof(key, self.epoch)?;
+        self.get_at(key, self.epoch)
+    }
+
+    fn get_at(&self, key: KeyHash, version: u64) -> Result<AccountResponse> {
+        ensure!(
+            version <= self.epoch,
+            "Version {} is not yet part of the tree",
+            version
+        );
+        let root = Digest(self.jmt.get_root_hash(version)?.0);
+        let (value, proof) = self.jmt.get_with_proof(key, version)?;
 
         match value {
             Some(serialized_value) => {
                 let deserialized_value = Account::decode_from_bytes(&serialized_value)?;
                 let membership_proof = MerkleProof { root, proof, key };
+                if deserialized_value.is_deactivated() {
+                    return Ok(Deactivated(Box::new(deserialized_value), membership_proof));
+                }
                 Ok(Found(Box::new(deserialized_value), membership_proof))
             }
             None => {
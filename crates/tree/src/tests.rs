@@ -1,8 +1,15 @@
 use std::sync::Arc;
 
 use jmt::{mock::MockTreeStore, KeyHash};
-use prism_common::{operation::SignatureBundle, transaction_builder::TransactionBuilder};
-use prism_keys::{CryptoAlgorithm, SigningKey};
+use prism_common::{
+    account::{AccountPolicy, KeyRole, RecoveryConfig},
+    allow_list::AllowList,
+    digest::Digest,
+    operation::{Operation, ServiceChallenge, ServiceChallengeInput, SignatureBundle},
+    params::{ProtocolParams, ValidationContext},
+    transaction_builder::TransactionBuilder,
+};
+use prism_keys::{CryptoAlgorithm, DecryptionKey, Signature, SigningKey};
 
 use crate::{
     hasher::TreeHasher, key_directory_tree::KeyDirectoryTree, proofs::Proof,
@@ -14,19 +21,19 @@ fn test_insert_and_get(algorithm: CryptoAlgorithm) {
     let mut tx_builder = TransactionBuilder::new();
 
     let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
-    let Proof::Insert(insert_proof) = tree.process_transaction(service_tx).unwrap() else {
+    let Proof::Insert(insert_proof) = tree.process_transaction(service_tx, 0).unwrap() else {
         panic!("Processing transaction did not return the expected insert proof");
     };
-    assert!(insert_proof.verify(None).is_ok());
+    assert!(insert_proof.verify(None, &ValidationContext::default()).is_ok());
 
     let account_tx =
         tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
 
-    let Proof::Insert(insert_proof) = tree.process_transaction(account_tx).unwrap() else {
+    let Proof::Insert(insert_proof) = tree.process_transaction(account_tx, 0).unwrap() else {
         panic!("Processing transaction did not return the expected insert proof");
     };
     let service_challenge = tx_builder.get_account("service_1").unwrap().service_challenge();
-    assert!(insert_proof.verify(service_challenge).is_ok());
+    assert!(insert_proof.verify(service_challenge, &ValidationContext::default()).is_ok());
 
     let Found(account, membership_proof) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap()
     else {
@@ -56,7 +63,7 @@ fn test_insert_for_nonexistent_service_fails(algorithm: CryptoAlgorithm) {
         )
         .build();
 
-    let insertion_result = tree.process_transaction(invalid_account_tx);
+    let insertion_result = tree.process_transaction(invalid_account_tx, 0);
     assert!(insertion_result.is_err());
 }
 
@@ -83,15 +90,67 @@ fn test_insert_with_invalid_service_challenge_fails(algorithm: CryptoAlgorithm)
         )
         .build();
 
-    let Proof::Insert(insert_proof) = tree.process_transaction(service_tx).unwrap() else {
+    let Proof::Insert(insert_proof) = tree.process_transaction(service_tx, 0).unwrap() else {
         panic!("Processing service registration failed")
     };
-    assert!(insert_proof.verify(None).is_ok());
+    assert!(insert_proof.verify(None, &ValidationContext::default()).is_ok());
 
-    let create_account_result = tree.process_transaction(acc_with_invalid_challenge_tx);
+    let create_account_result = tree.process_transaction(acc_with_invalid_challenge_tx, 0);
     assert!(create_account_result.is_err());
 }
 
+fn test_account_creation_cannot_be_front_run(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+
+    let account_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").build();
+    let Some(Operation::CreateAccount {
+        id,
+        service_id,
+        challenge,
+        key,
+        policy,
+        delegate_to_service,
+        ..
+    }) = account_tx.operations.first().cloned()
+    else {
+        panic!("Expected an account creation");
+    };
+
+    // Copying the service credentials into a creation with other fields
+    // requires a signature by the initial key
+    let attacker_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let tampered_fields = [
+        (Some(0), policy.clone(), delegate_to_service),
+        (
+            None,
+            AccountPolicy::threshold(policy.threshold + 1),
+            delegate_to_service,
+        ),
+        (None, policy.clone(), !delegate_to_service),
+    ];
+    for (valid_until, policy, delegate_to_service) in tampered_fields {
+        let tampered_op = Operation::CreateAccount {
+            id: id.clone(),
+            service_id: service_id.clone(),
+            challenge: challenge.clone(),
+            key: key.clone(),
+            valid_until,
+            policy,
+            delegate_to_service,
+        };
+        let front_running_tx =
+            tx_builder.operations("acc_1", vec![tampered_op], &attacker_key).build();
+        assert!(tree.process_transaction(front_running_tx, 0).is_err());
+    }
+
+    assert!(tree.process_transaction(account_tx, 0).is_ok());
+}
+
 fn test_insert_duplicate_key(algorithm: CryptoAlgorithm) {
     let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
     let mut tx_builder = TransactionBuilder::new();
@@ -102,18 +161,18 @@ fn test_insert_duplicate_key(algorithm: CryptoAlgorithm) {
     let account_with_same_id_tx =
         tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").build();
 
-    let Proof::Insert(insert_proof) = tree.process_transaction(service_tx).unwrap() else {
+    let Proof::Insert(insert_proof) = tree.process_transaction(service_tx, 0).unwrap() else {
         panic!("Processing service registration failed")
     };
-    assert!(insert_proof.verify(None).is_ok());
+    assert!(insert_proof.verify(None, &ValidationContext::default()).is_ok());
 
-    let Proof::Insert(insert_proof) = tree.process_transaction(account_tx).unwrap() else {
+    let Proof::Insert(insert_proof) = tree.process_transaction(account_tx, 0).unwrap() else {
         panic!("Processing Account creation failed")
     };
     let service_challenge = tx_builder.get_account("service_1").unwrap().service_challenge();
-    assert!(insert_proof.verify(service_challenge).is_ok());
+    assert!(insert_proof.verify(service_challenge, &ValidationContext::default()).is_ok());
 
-    let create_acc_with_same_id_result = tree.process_transaction(account_with_same_id_tx);
+    let create_acc_with_same_id_result = tree.process_transaction(account_with_same_id_tx, 0);
     assert!(create_acc_with_same_id_result.is_err());
 }
 
@@ -125,15 +184,15 @@ fn test_update_existing_key(algorithm: CryptoAlgorithm) {
     let acc_tx =
         tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
 
-    tree.process_transaction(service_tx).unwrap();
-    tree.process_transaction(acc_tx).unwrap();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
 
     let key_tx = tx_builder.add_random_key_verified_with_root(algorithm, "acc_1").commit();
 
-    let Proof::Update(update_proof) = tree.process_transaction(key_tx).unwrap() else {
+    let Proof::Update(update_proof) = tree.process_transaction(key_tx, 0).unwrap() else {
         panic!("Processing key update failed")
     };
-    assert!(update_proof.verify().is_ok());
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
 
     let get_result = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap();
     let test_account = tx_builder.get_account("acc_1").unwrap();
@@ -147,7 +206,7 @@ fn test_update_non_existing_key(algorithm: CryptoAlgorithm) {
 
     let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
 
-    tree.process_transaction(service_tx).unwrap();
+    tree.process_transaction(service_tx, 0).unwrap();
 
     // This is a signing key not known to the storage yet
     let random_signing_key =
@@ -155,36 +214,1147 @@ fn test_update_non_existing_key(algorithm: CryptoAlgorithm) {
     // This transaction shall be invalid, because it is signed with an unknown key
     let invalid_key_tx = tx_builder.add_random_key(algorithm, "acc_1", &random_signing_key).build();
 
-    let result = tree.process_transaction(invalid_key_tx);
+    let result = tree.process_transaction(invalid_key_tx, 0);
     assert!(result.is_err());
 }
 
+fn test_expired_key_cannot_sign(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    // Key is usable up to and including epoch 1
+    let expiring_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let root_key = tx_builder.get_account_keys().get("acc_1").unwrap()[0].clone();
+    let add_key_tx = tx_builder
+        .add_key_valid_until("acc_1", expiring_key.verifying_key(), Some(1), &root_key)
+        .commit();
+    let Proof::Update(update_proof) = tree.process_transaction(add_key_tx, 0).unwrap() else {
+        panic!("Processing key update failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
+
+    tx_builder.set_epoch(1);
+    let valid_data_tx = tx_builder
+        .add_randomly_signed_data(algorithm, "acc_1", b"data".to_vec(), &expiring_key)
+        .commit();
+    let Proof::Update(update_proof) = tree.process_transaction(valid_data_tx, 1).unwrap() else {
+        panic!("Processing data update failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::at_epoch(1)).is_ok());
+    // The same proof must not verify once the signing key has expired
+    assert!(update_proof.verify(None, &ValidationContext::at_epoch(2)).is_err());
+
+    let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap() else {
+        panic!("Expected account to be found");
+    };
+    assert!(account.expired_keys(1).is_empty());
+    assert_eq!(account.expired_keys(2), vec![&expiring_key.verifying_key()]);
+
+    let expired_data_tx = tx_builder
+        .add_randomly_signed_data(algorithm, "acc_1", b"data".to_vec(), &expiring_key)
+        .build();
+    assert!(tree.process_transaction(expired_data_tx, 2).is_err());
+
+    // Keys must not be added with an expiry in the past
+    let already_expired_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    tx_builder.set_epoch(2);
+    let already_expired_tx = tx_builder
+        .add_key_valid_until(
+            "acc_1",
+            already_expired_key.verifying_key(),
+            Some(1),
+            &root_key,
+        )
+        .build();
+    assert!(tree.process_transaction(already_expired_tx, 2).is_err());
+}
+
+fn test_threshold_policy(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    let root_key = tx_builder.get_account_keys().get("acc_1").unwrap()[0].clone();
+    let second_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let third_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+
+    let add_second_tx =
+        tx_builder.add_key_verified_with_root("acc_1", second_key.verifying_key()).commit();
+    let add_third_tx =
+        tx_builder.add_key_verified_with_root("acc_1", third_key.verifying_key()).commit();
+    let policy_tx =
+        tx_builder.set_policy_verified_with_root("acc_1", AccountPolicy::threshold(2)).commit();
+    tree.process_transaction(add_second_tx, 0).unwrap();
+    tree.process_transaction(add_third_tx, 0).unwrap();
+    tree.process_transaction(policy_tx, 0).unwrap();
+
+    // A single key can no longer revoke other keys
+    let single_signed_tx =
+        tx_builder.revoke_key("acc_1", third_key.verifying_key(), &root_key).build();
+    assert!(tree.process_transaction(single_signed_tx, 0).is_err());
+
+    // Cosignatures by keys that don't belong to the account don't count
+    let foreign_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let foreign_cosigned_tx = tx_builder
+        .revoke_key("acc_1", third_key.verifying_key(), &root_key)
+        .cosign(&foreign_key)
+        .build();
+    assert!(tree.process_transaction(foreign_cosigned_tx, 0).is_err());
+
+    let cosigned_tx = tx_builder
+        .revoke_key("acc_1", third_key.verifying_key(), &root_key)
+        .cosign(&second_key)
+        .commit();
+    let Proof::Update(update_proof) = tree.process_transaction(cosigned_tx, 0).unwrap() else {
+        panic!("Processing key revocation failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
+
+    // Operations must not leave fewer admin keys than the threshold
+    let below_threshold_tx = tx_builder
+        .revoke_key("acc_1", second_key.verifying_key(), &root_key)
+        .cosign(&second_key)
+        .build();
+    assert!(tree.process_transaction(below_threshold_tx, 0).is_err());
+    let excessive_policy_tx = tx_builder
+        .set_policy("acc_1", AccountPolicy::threshold(3), &root_key)
+        .cosign(&second_key)
+        .build();
+    assert!(tree.process_transaction(excessive_policy_tx, 0).is_err());
+
+    // Non-sensitive operations still only need a single signature
+    let data_tx = tx_builder
+        .add_internally_signed_data_verified_with_root("acc_1", b"data".to_vec())
+        .commit();
+    assert!(tree.process_transaction(data_tx, 0).is_ok());
+
+    let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap() else {
+        panic!("Expected account to be found");
+    };
+    assert_eq!(account.policy(), &AccountPolicy::threshold(2));
+    assert!(!account.contains_key(&third_key.verifying_key()));
+
+    // Once keys expire below the threshold, sensitive operations fail
+    // instead of requiring fewer signatures
+    let expiring_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let add_expiring_tx = tx_builder
+        .add_key_valid_until("acc_1", expiring_key.verifying_key(), Some(1), &root_key)
+        .cosign(&second_key)
+        .commit();
+    let policy_tx = tx_builder
+        .set_policy("acc_1", AccountPolicy::threshold(3), &root_key)
+        .cosign(&second_key)
+        .commit();
+    tree.process_transaction(add_expiring_tx, 0).unwrap();
+    tree.process_transaction(policy_tx, 0).unwrap();
+
+    tx_builder.set_epoch(2);
+    let expired_threshold_tx = tx_builder
+        .add_key_valid_until("acc_1", third_key.verifying_key(), None, &root_key)
+        .cosign(&second_key)
+        .build();
+    assert!(tree.process_transaction(expired_threshold_tx, 2).is_err());
+}
+
+fn test_key_roles(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    let root_key = tx_builder.get_account_keys().get("acc_1").unwrap()[0].clone();
+    let data_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let signing_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+
+    let add_data_key_tx = tx_builder
+        .add_key_with_role(
+            "acc_1",
+            data_key.verifying_key(),
+            KeyRole::DataOnly,
+            &root_key,
+        )
+        .commit();
+    let Proof::Update(update_proof) = tree.process_transaction(add_data_key_tx, 0).unwrap() else {
+        panic!("Processing key addition failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
+    let add_signing_key_tx = tx_builder
+        .add_key_with_role(
+            "acc_1",
+            signing_key.verifying_key(),
+            KeyRole::SigningOnly,
+            &root_key,
+        )
+        .commit();
+    tree.process_transaction(add_signing_key_tx, 0).unwrap();
+
+    // Scoped keys don't count towards the policy threshold
+    let policy_tx =
+        tx_builder.set_policy_verified_with_root("acc_1", AccountPolicy::threshold(2)).build();
+    assert!(tree.process_transaction(policy_tx, 0).is_err());
+
+    // Data-only keys can manage data, but not keys
+    let data_tx =
+        tx_builder.add_internally_signed_data("acc_1", b"data".to_vec(), &data_key).commit();
+    let Proof::Update(update_proof) = tree.process_transaction(data_tx, 0).unwrap() else {
+        panic!("Processing data addition failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
+
+    let revoke_root_tx =
+        tx_builder.revoke_key("acc_1", root_key.verifying_key(), &data_key).build();
+    assert!(tree.process_transaction(revoke_root_tx, 0).is_err());
+    let add_key_tx = tx_builder.add_random_key(algorithm, "acc_1", &data_key).build();
+    assert!(tree.process_transaction(add_key_tx, 0).is_err());
+
+    // Scoped keys cannot authorize operations as cosigners either
+    let cosigned_tx = tx_builder
+        .revoke_key("acc_1", data_key.verifying_key(), &root_key)
+        .cosign(&data_key)
+        .build();
+    assert!(tree.process_transaction(cosigned_tx, 0).is_err());
+
+    // Signing-only keys can sign data added by other keys, but cannot
+    // authorize any operation
+    let signing_only_tx =
+        tx_builder.add_internally_signed_data("acc_1", b"other".to_vec(), &signing_key).build();
+    assert!(tree.process_transaction(signing_only_tx, 0).is_err());
+    let signed_data_tx =
+        tx_builder.add_signed_data("acc_1", b"signed".to_vec(), &signing_key, &data_key).commit();
+    tree.process_transaction(signed_data_tx, 0).unwrap();
+
+    // Data attributed to another key of the account needs that key's signature
+    let forged_bundle = SignatureBundle {
+        verifying_key: root_key.verifying_key(),
+        signature: data_key.sign(b"forged"),
+    };
+    let forged_data_tx = tx_builder
+        .add_pre_signed_data("acc_1", b"forged".to_vec(), forged_bundle, &data_key)
+        .build();
+    assert!(tree.process_transaction(forged_data_tx, 0).is_err());
+
+    let revoke_tx = tx_builder.revoke_key("acc_1", data_key.verifying_key(), &root_key).commit();
+    tree.process_transaction(revoke_tx, 0).unwrap();
+
+    let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap() else {
+        panic!("Expected account to be found");
+    };
+    assert_eq!(account.signed_data().len(), 2);
+    assert!(!account.contains_key(&data_key.verifying_key()));
+    assert_eq!(
+        account.get_key(&signing_key.verifying_key()).unwrap().role,
+        KeyRole::SigningOnly
+    );
+}
+
+fn test_timelocked_recovery(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    let root_key = tx_builder.get_account_keys().get("acc_1").unwrap()[0].clone();
+    let recovery_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let new_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+
+    // Recovery is not possible before recovery keys are set
+    let no_recovery_tx =
+        tx_builder.initiate_recovery("acc_1", new_key.verifying_key(), &recovery_key).build();
+    assert!(tree.process_transaction(no_recovery_tx, 0).is_err());
+
+    let recovery = RecoveryConfig::new(vec![recovery_key.verifying_key()], 2);
+    let set_recovery_tx =
+        tx_builder.set_recovery_verified_with_root("acc_1", Some(recovery)).commit();
+    tree.process_transaction(set_recovery_tx, 0).unwrap();
+
+    // Only recovery keys can initiate recovery
+    let invalid_initiate_tx =
+        tx_builder.initiate_recovery("acc_1", new_key.verifying_key(), &root_key).build();
+    assert!(tree.process_transaction(invalid_initiate_tx, 0).is_err());
+
+    // A recovery initiated in epoch 1 can be cancelled by the account before epoch 3
+    tx_builder.set_epoch(1);
+    let initiate_tx =
+        tx_builder.initiate_recovery("acc_1", new_key.verifying_key(), &recovery_key).commit();
+    tree.process_transaction(initiate_tx, 1).unwrap();
+
+    tx_builder.set_epoch(2);
+    let early_complete_tx = tx_builder.complete_recovery("acc_1", &recovery_key).build();
+    assert!(tree.process_transaction(early_complete_tx, 2).is_err());
+
+    let cancel_tx = tx_builder.cancel_recovery_verified_with_root("acc_1").commit();
+    let Proof::Update(update_proof) = tree.process_transaction(cancel_tx, 2).unwrap() else {
+        panic!("Processing recovery cancellation failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::at_epoch(2)).is_ok());
+
+    tx_builder.set_epoch(3);
+    let cancelled_complete_tx = tx_builder.complete_recovery("acc_1", &recovery_key).build();
+    assert!(tree.process_transaction(cancelled_complete_tx, 3).is_err());
+
+    // Without cancellation, the recovery completes after the delay
+    let initiate_tx =
+        tx_builder.initiate_recovery("acc_1", new_key.verifying_key(), &recovery_key).commit();
+    tree.process_transaction(initiate_tx, 3).unwrap();
+
+    tx_builder.set_epoch(5);
+    let complete_tx = tx_builder.complete_recovery("acc_1", &recovery_key).commit();
+    let Proof::Update(update_proof) = tree.process_transaction(complete_tx, 5).unwrap() else {
+        panic!("Processing recovery completion failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::at_epoch(5)).is_ok());
+    // The same completion would not have been valid before the delay passed
+    assert!(update_proof.verify(None, &ValidationContext::at_epoch(4)).is_err());
+
+    let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap() else {
+        panic!("Expected account to be found");
+    };
+    assert!(account.contains_key(&new_key.verifying_key()));
+    assert!(!account.contains_key(&root_key.verifying_key()));
+    assert!(account.pending_recovery().is_none());
+
+    let old_key_tx = tx_builder
+        .add_randomly_signed_data(algorithm, "acc_1", b"data".to_vec(), &root_key)
+        .build();
+    assert!(tree.process_transaction(old_key_tx, 5).is_err());
+
+    let new_key_tx = tx_builder
+        .add_randomly_signed_data(algorithm, "acc_1", b"data".to_vec(), &new_key)
+        .commit();
+    assert!(tree.process_transaction(new_key_tx, 5).is_ok());
+}
+
+fn test_remove_data(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    for data in [b"entry 0", b"entry 1", b"entry 2", b"entry 3"] {
+        let data_tx = tx_builder
+            .add_randomly_signed_data_verified_with_root(algorithm, "acc_1", data.to_vec())
+            .commit();
+        tree.process_transaction(data_tx, 0).unwrap();
+    }
+
+    let remove_by_index_tx =
+        tx_builder.remove_data_by_index_verified_with_root("acc_1", 1).commit();
+    let Proof::Update(update_proof) = tree.process_transaction(remove_by_index_tx, 0).unwrap()
+    else {
+        panic!("Processing data removal failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
+
+    let remove_by_digest_tx = tx_builder
+        .remove_data_by_digest_verified_with_root("acc_1", Digest::hash(b"entry 3"))
+        .commit();
+    tree.process_transaction(remove_by_digest_tx, 0).unwrap();
+
+    let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap() else {
+        panic!("Expected account to be found");
+    };
+    let remaining: Vec<&[u8]> = account.signed_data().iter().map(|d| d.data.as_slice()).collect();
+    assert_eq!(
+        remaining,
+        vec![b"entry 0".as_slice(), b"entry 2".as_slice()]
+    );
+
+    // Removing entries that don't exist fails
+    let out_of_bounds_tx = tx_builder.remove_data_by_index_verified_with_root("acc_1", 2).build();
+    assert!(tree.process_transaction(out_of_bounds_tx, 0).is_err());
+
+    let unknown_digest_tx = tx_builder
+        .remove_data_by_digest_verified_with_root("acc_1", Digest::hash(b"entry 3"))
+        .build();
+    assert!(tree.process_transaction(unknown_digest_tx, 0).is_err());
+}
+
+fn test_service_challenge_rotation(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let old_challenge_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let new_challenge_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let service_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+
+    let service_tx = tx_builder
+        .register_service("service_1", old_challenge_key.clone(), service_key.clone())
+        .commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+
+    // Only the service itself can rotate its challenge
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    let acc_key = tx_builder.get_account_keys().get("acc_1").unwrap()[0].clone();
+    let non_service_tx =
+        tx_builder.update_service_challenge("acc_1", new_challenge_key.clone(), &acc_key).build();
+    let rotate_tx = tx_builder
+        .update_service_challenge("service_1", new_challenge_key.clone(), &service_key)
+        .commit();
+    let new_challenge_acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_2", "service_1").commit();
+    let old_challenge_acc_tx = tx_builder
+        .create_account_with_random_key(algorithm, "acc_3", "service_1", &old_challenge_key)
+        .build();
+
+    let batch = tree
+        .process_batch(
+            vec![
+                acc_tx,
+                non_service_tx,
+                rotate_tx,
+                new_challenge_acc_tx,
+                old_challenge_acc_tx,
+            ],
+            0,
+        )
+        .unwrap();
+
+    // acc_1 was created against the old challenge, acc_2 against the new one
+    assert_eq!(batch.proofs.len(), 3);
+    assert!(batch.verify().is_ok());
+
+    assert!(matches!(
+        tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap(),
+        Found(_, _)
+    ));
+    assert!(matches!(
+        tree.get(KeyHash::with::<TreeHasher>("acc_2")).unwrap(),
+        Found(_, _)
+    ));
+    assert!(matches!(
+        tree.get(KeyHash::with::<TreeHasher>("acc_3")).unwrap(),
+        NotFound(_)
+    ));
+
+    let Found(service, _) = tree.get(KeyHash::with::<TreeHasher>("service_1")).unwrap() else {
+        panic!("Expected service to be found");
+    };
+    assert_eq!(
+        service.service_challenge(),
+        Some(&ServiceChallenge::Signed(new_challenge_key.verifying_key()))
+    );
+}
+
+fn test_service_creation_gates(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let random_key = || SigningKey::new_with_algorithm(algorithm).unwrap();
+    let challenge_keys = [random_key(), random_key(), random_key()];
+    let aggregate_keys = [
+        SigningKey::new_bls12381(),
+        SigningKey::new_bls12381(),
+        SigningKey::new_bls12381(),
+    ];
+    let proven_key = |key: &SigningKey| {
+        key.verifying_key().verify_possession(&key.prove_possession().unwrap()).unwrap()
+    };
+    let allow_list = AllowList::new(vec![
+        "alice".to_string(),
+        "bob".to_string(),
+        "carol".to_string(),
+    ]);
+
+    let gates = [
+        ("open_service", ServiceChallenge::Open),
+        (
+            "allow_list_service",
+            ServiceChallenge::AllowList(allow_list.root()),
+        ),
+        (
+            "threshold_service",
+            ServiceChallenge::Threshold {
+                keys: challenge_keys.iter().map(SigningKey::verifying_key).collect(),
+                threshold: 2,
+            },
+        ),
+        (
+            "aggregate_service",
+            ServiceChallenge::AggregateThreshold {
+                keys: aggregate_keys.iter().map(proven_key).collect(),
+                threshold: 2,
+            },
+        ),
+    ];
+    for (id, gate) in gates {
+        let service_tx =
+            tx_builder.register_service_with_challenge(id, gate, random_key()).commit();
+        tree.process_transaction(service_tx, 0).unwrap();
+    }
+
+    let invalid_threshold_tx = tx_builder
+        .register_service_with_challenge(
+            "invalid_service",
+            ServiceChallenge::Threshold {
+                keys: vec![random_key().verifying_key()],
+                threshold: 2,
+            },
+            random_key(),
+        )
+        .build();
+    assert!(tree.process_transaction(invalid_threshold_tx, 0).is_err());
+    let duplicate_keys_tx = tx_builder
+        .register_service_with_challenge(
+            "duplicate_keys_service",
+            ServiceChallenge::AggregateThreshold {
+                keys: vec![
+                    proven_key(&aggregate_keys[0]),
+                    proven_key(&aggregate_keys[0]),
+                ],
+                threshold: 2,
+            },
+            random_key(),
+        )
+        .build();
+    assert!(tree.process_transaction(duplicate_keys_tx, 0).is_err());
+
+    let mut create_account = |tree: &mut KeyDirectoryTree<MockTreeStore>,
+                              id: &str,
+                              service_id: &str,
+                              input: ServiceChallengeInput| {
+        let account_tx = tx_builder
+            .create_account_with_challenge_input(id, service_id, input, random_key())
+            .build();
+        let Proof::Insert(insert_proof) = tree.process_transaction(account_tx, 0)? else {
+            panic!("Processing transaction did not return the expected insert proof");
+        };
+        let service = tree.get(KeyHash::with::<TreeHasher>(service_id))?;
+        let Found(service, _) = service else {
+            panic!("Expected service to be found");
+        };
+        insert_proof.verify(service.service_challenge(), &ValidationContext::default())
+    };
+
+    // Open services accept anyone, but only with the matching input
+    assert!(create_account(
+        &mut tree,
+        "acc_1",
+        "open_service",
+        ServiceChallengeInput::Open
+    )
+    .is_ok());
+    let signed_input = ServiceChallengeInput::Signed(random_key().sign(b"acc_2"));
+    assert!(create_account(&mut tree, "acc_2", "open_service", signed_input).is_err());
+
+    // Allow listed ids need a valid inclusion proof
+    let bob_proof = allow_list.prove("bob").unwrap();
+    let mallory_input = ServiceChallengeInput::AllowList(bob_proof.clone());
+    assert!(create_account(&mut tree, "mallory", "allow_list_service", mallory_input).is_err());
+    let bob_input = ServiceChallengeInput::AllowList(bob_proof);
+    assert!(create_account(&mut tree, "bob", "allow_list_service", bob_input).is_ok());
+
+    // Threshold services need enough distinct signatures by challenge keys
+    let threshold_input = |id: &str, signers: &[&SigningKey]| {
+        let key = random_key();
+        let hash =
+            ServiceChallenge::credentials_digest(id, "threshold_service", &key.verifying_key());
+        let bundles = signers
+            .iter()
+            .map(|signer| SignatureBundle {
+                verifying_key: signer.verifying_key(),
+                signature: signer.sign(&hash.to_bytes()),
+            })
+            .collect();
+        (key, ServiceChallengeInput::Threshold(bundles))
+    };
+    for (id, signers, expect_ok) in [
+        ("acc_3", vec![&challenge_keys[0]], false),
+        ("acc_4", vec![&challenge_keys[0], &challenge_keys[0]], false),
+        ("acc_5", vec![&challenge_keys[0], &random_key()], false),
+        ("acc_6", vec![&challenge_keys[0], &challenge_keys[2]], true),
+    ] {
+        let (key, input) = threshold_input(id, &signers);
+        let account_tx = tx_builder
+            .create_account_with_challenge_input(id, "threshold_service", input, key)
+            .build();
+        assert_eq!(tree.process_transaction(account_tx, 0).is_ok(), expect_ok);
+    }
+
+    // Aggregate threshold services need one signature aggregated from enough
+    // distinct challenge keys, whose indices are given
+    let aggregate_input = |id: &str, indices: Vec<u32>, signers: &[&SigningKey]| {
+        let key = random_key();
+        let hash =
+            ServiceChallenge::credentials_digest(id, "aggregate_service", &key.verifying_key());
+        let signatures: Vec<Signature> =
+            signers.iter().map(|signer| signer.sign(&hash.to_bytes())).collect();
+        let input = ServiceChallengeInput::AggregateThreshold {
+            signers: indices,
+            signature: Signature::aggregate(&signatures).unwrap(),
+        };
+        (key, input)
+    };
+    for (id, indices, signers, expect_ok) in [
+        ("acc_7", vec![0], vec![&aggregate_keys[0]], false),
+        (
+            "acc_8",
+            vec![0, 0],
+            vec![&aggregate_keys[0], &aggregate_keys[0]],
+            false,
+        ),
+        (
+            "acc_9",
+            vec![0, 3],
+            vec![&aggregate_keys[0], &aggregate_keys[2]],
+            false,
+        ),
+        (
+            "acc_10",
+            vec![0, 2],
+            vec![&aggregate_keys[0], &aggregate_keys[1]],
+            false,
+        ),
+        (
+            "acc_11",
+            vec![0, 2],
+            vec![&aggregate_keys[0], &aggregate_keys[2]],
+            true,
+        ),
+    ] {
+        let (key, input) = aggregate_input(id, indices, &signers);
+        let account_tx = tx_builder
+            .create_account_with_challenge_input(id, "aggregate_service", input, key)
+            .build();
+        assert_eq!(tree.process_transaction(account_tx, 0).is_ok(), expect_ok);
+    }
+}
+
+fn test_deactivate_account(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    let data_tx = tx_builder
+        .add_internally_signed_data_verified_with_root("acc_1", b"personal data".to_vec())
+        .commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+    tree.process_transaction(data_tx, 0).unwrap();
+
+    let root_key = tx_builder.get_account_keys().get("acc_1").unwrap()[0].clone();
+    let deactivate_tx = tx_builder.deactivate_account_verified_with_root("acc_1").commit();
+    let Proof::Update(update_proof) = tree.process_transaction(deactivate_tx, 0).unwrap() else {
+        panic!("Processing deactivation failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
+
+    let Deactivated(account, membership_proof) =
+        tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap()
+    else {
+        panic!("Expected account to be deactivated");
+    };
+    assert!(membership_proof.verify_existence(&account).is_ok());
+    assert!(account.valid_keys().is_empty());
+    assert!(account.signed_data().is_empty());
+
+    // Deactivated accounts can neither be modified nor re-created
+    let add_key_tx = tx_builder.add_random_key(algorithm, "acc_1", &root_key).build();
+    assert!(tree.process_transaction(add_key_tx, 0).is_err());
+
+    let recreate_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").build();
+    assert!(tree.process_transaction(recreate_tx, 0).is_err());
+}
+
+fn test_multi_operation_transaction(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    let old_key = tx_builder.get_account_keys().get("acc_1").unwrap()[0].clone();
+    let new_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let data_bundle = SignatureBundle {
+        verifying_key: new_key.verifying_key(),
+        signature: new_key.sign(b"data"),
+    };
+
+    // A failing operation reverts the whole transaction
+    let failing_tx = tx_builder
+        .operations(
+            "acc_1",
+            vec![
+                Operation::AddData {
+                    data: b"data".to_vec(),
+                    data_signature: data_bundle.clone(),
+                },
+                Operation::RevokeKey {
+                    key: new_key.verifying_key(),
+                },
+            ],
+            &old_key,
+        )
+        .build();
+    assert!(tree.process_transaction(failing_tx, 0).is_err());
+
+    // Keys can be rotated with a single transaction and proof
+    let rotate_tx = tx_builder
+        .operations(
+            "acc_1",
+            vec![
+                Operation::AddKey {
+                    key: new_key.verifying_key(),
+                    valid_until: None,
+                    role: KeyRole::Admin,
+                },
+                Operation::RevokeKey {
+                    key: old_key.verifying_key(),
+                },
+            ],
+            &old_key,
+        )
+        .commit();
+    let Proof::Update(update_proof) = tree.process_transaction(rotate_tx, 0).unwrap() else {
+        panic!("Processing key rotation failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
+
+    let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap() else {
+        panic!("Expected account to be found");
+    };
+    assert_eq!(account.nonce(), 2);
+    assert!(account.signed_data().is_empty());
+    assert!(account.contains_key(&new_key.verifying_key()));
+    assert!(!account.contains_key(&old_key.verifying_key()));
+
+    // Accounts can be created with initial data, but creation has to come first
+    let acc_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let service_challenge_key = tx_builder.get_service_keys().get("service_1").unwrap().clone();
+    let credentials =
+        ServiceChallenge::credentials_digest("acc_2", "service_1", &acc_key.verifying_key());
+    let create_op = Operation::CreateAccount {
+        id: "acc_2".to_string(),
+        service_id: "service_1".to_string(),
+        challenge: ServiceChallengeInput::Signed(
+            service_challenge_key.sign(&credentials.to_bytes()),
+        ),
+        key: acc_key.verifying_key(),
+        valid_until: None,
+        policy: AccountPolicy::default(),
+        delegate_to_service: false,
+    };
+    let add_data_op = Operation::AddData {
+        data: b"data".to_vec(),
+        data_signature: data_bundle,
+    };
+
+    let misordered_tx = tx_builder
+        .operations(
+            "acc_2",
+            vec![add_data_op.clone(), create_op.clone()],
+            &acc_key,
+        )
+        .build();
+    assert!(tree.process_transaction(misordered_tx, 0).is_err());
+
+    let create_tx = tx_builder.operations("acc_2", vec![create_op, add_data_op], &acc_key).commit();
+    let Proof::Insert(insert_proof) = tree.process_transaction(create_tx, 0).unwrap() else {
+        panic!("Processing account creation failed")
+    };
+    let service_challenge = tx_builder.get_account("service_1").unwrap().service_challenge();
+    assert!(insert_proof.verify(service_challenge, &ValidationContext::default()).is_ok());
+
+    let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_2")).unwrap() else {
+        panic!("Expected account to be found");
+    };
+    assert_eq!(account.nonce(), 1);
+    assert_eq!(account.signed_data().len(), 1);
+}
+
+fn test_protocol_params_limits(algorithm: CryptoAlgorithm) {
+    let params = ProtocolParams {
+        max_data_size: 8,
+        max_keys_per_account: 2,
+        max_signed_data_entries: 1,
+        max_id_length: 16,
+        allowed_algorithms: vec![algorithm],
+        ..Default::default()
+    };
+    let mut tree =
+        KeyDirectoryTree::new_with_params(Arc::new(MockTreeStore::default()), params.clone());
+    let mut tx_builder = TransactionBuilder::new();
+    tx_builder.set_params(params);
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    let long_id_tx = tx_builder
+        .create_account_with_random_key_signed(algorithm, "an_overly_long_account_id", "service_1")
+        .build();
+    assert!(tree.process_transaction(long_id_tx, 0).is_err());
+
+    let other_algorithm = if algorithm == CryptoAlgorithm::Ed25519 {
+        CryptoAlgorithm::Secp256k1
+    } else {
+        CryptoAlgorithm::Ed25519
+    };
+    let disallowed_key_tx =
+        tx_builder.add_random_key_verified_with_root(other_algorithm, "acc_1").build();
+    assert!(tree.process_transaction(disallowed_key_tx, 0).is_err());
+
+    let oversized_data_tx = tx_builder
+        .add_randomly_signed_data_verified_with_root(algorithm, "acc_1", vec![0; 9])
+        .build();
+    assert!(tree.process_transaction(oversized_data_tx, 0).is_err());
+
+    let add_key_tx = tx_builder.add_random_key_verified_with_root(algorithm, "acc_1").commit();
+    let data_tx = tx_builder
+        .add_randomly_signed_data_verified_with_root(algorithm, "acc_1", vec![0; 8])
+        .commit();
+    let mut batch = tree.process_batch(vec![add_key_tx, data_tx], 0).unwrap();
+    assert_eq!(batch.proofs.len(), 2);
+    assert!(batch.verify().is_ok());
+
+    // The circuit validates against the parameters carried by the batch
+    batch.params.max_signed_data_entries = 0;
+    assert!(batch.verify().is_err());
+
+    let third_key_tx = tx_builder.add_random_key_verified_with_root(algorithm, "acc_1").build();
+    assert!(tree.process_transaction(third_key_tx, 0).is_err());
+
+    let second_data_tx =
+        tx_builder.add_randomly_signed_data_verified_with_root(algorithm, "acc_1", vec![1]).build();
+    assert!(tree.process_transaction(second_data_tx, 0).is_err());
+}
+
+fn test_chain_id_replay_protection(algorithm: CryptoAlgorithm) {
+    let staging_params = ProtocolParams::for_chain("staging");
+    let mut staging_tree = KeyDirectoryTree::new_with_params(
+        Arc::new(MockTreeStore::default()),
+        staging_params.clone(),
+    );
+    let mut production_tree = KeyDirectoryTree::new_with_params(
+        Arc::new(MockTreeStore::default()),
+        ProtocolParams::for_chain("production"),
+    );
+    let mut tx_builder = TransactionBuilder::new();
+    tx_builder.set_params(staging_params);
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+
+    // Transactions signed for staging are rejected on other chains
+    assert!(production_tree.process_transaction(service_tx.clone(), 0).is_err());
+    assert!(production_tree.process_transaction(acc_tx.clone(), 0).is_err());
+
+    let batch = staging_tree.process_batch(vec![service_tx, acc_tx], 0).unwrap();
+    assert_eq!(batch.proofs.len(), 2);
+    assert!(batch.verify().is_ok());
+
+    let Proof::Insert(insert_proof) = &batch.proofs[0] else {
+        panic!("Expected insert proof");
+    };
+    assert!(insert_proof.verify(None, &ValidationContext::default()).is_err());
+}
+
+fn test_transaction_expiry(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    tx_builder.set_transaction_valid_until(Some(1));
+    let data_tx =
+        tx_builder.add_randomly_signed_data_verified_with_root(algorithm, "acc_1", vec![1]).build();
+
+    assert!(tree.process_transaction(data_tx.clone(), 2).is_err());
+
+    // The expiry is covered by the signature
+    let mut extended_tx = data_tx.clone();
+    extended_tx.valid_until = Some(2);
+    assert!(tree.process_transaction(extended_tx, 2).is_err());
+
+    let Proof::Update(update_proof) = tree.process_transaction(data_tx, 1).unwrap() else {
+        panic!("Processing unexpired transaction failed")
+    };
+    assert!(update_proof.verify(None, &ValidationContext::at_epoch(1)).is_ok());
+    assert!(update_proof.verify(None, &ValidationContext::at_epoch(2)).is_err());
+}
+
+fn test_service_delegation(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let challenge_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let service_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let service_tx =
+        tx_builder.register_service("service_1", challenge_key, service_key.clone()).commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+
+    let old_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let delegating_acc_tx =
+        tx_builder.create_delegating_account_signed("acc_1", "service_1", old_key.clone()).commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_2", "service_1").commit();
+    tree.process_transaction(delegating_acc_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    // Only accounts that opted in can be managed by their service
+    let undelegated_tx = tx_builder
+        .delegated_add_key(
+            "acc_2",
+            SigningKey::new_with_algorithm(algorithm).unwrap(),
+            &service_key,
+        )
+        .build();
+    assert!(tree.process_transaction(undelegated_tx, 0).is_err());
+
+    // Delegated transactions must be signed by the service's keys
+    let foreign_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let foreign_tx = tx_builder
+        .delegated_add_key(
+            "acc_1",
+            SigningKey::new_with_algorithm(algorithm).unwrap(),
+            &foreign_key,
+        )
+        .build();
+    assert!(tree.process_transaction(foreign_tx, 0).is_err());
+
+    // The service replaces the lost key of the account
+    let new_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let add_tx = tx_builder.delegated_add_key("acc_1", new_key.clone(), &service_key).commit();
+    let revoke_tx =
+        tx_builder.delegated_revoke_key("acc_1", old_key.verifying_key(), &service_key).commit();
+    let batch = tree.process_batch(vec![add_tx, revoke_tx], 0).unwrap();
+    assert_eq!(batch.proofs.len(), 2);
+    assert!(batch.service_proofs.contains_key("service_1"));
+    assert!(batch.verify().is_ok());
+
+    let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap() else {
+        panic!("Expected account to be found");
+    };
+    assert_eq!(account.delegate(), Some("service_1"));
+    assert!(account.contains_key(&new_key.verifying_key()));
+    assert!(!account.contains_key(&old_key.verifying_key()));
+
+    // Once revoked, the service loses its authority over the account
+    let revoke_delegation_tx = tx_builder.revoke_delegation("acc_1", &new_key).commit();
+    tree.process_transaction(revoke_delegation_tx, 0).unwrap();
+
+    let after_revocation_tx = tx_builder
+        .delegated_add_key(
+            "acc_1",
+            SigningKey::new_with_algorithm(algorithm).unwrap(),
+            &service_key,
+        )
+        .build();
+    assert!(tree.process_transaction(after_revocation_tx, 0).is_err());
+
+    // Recovering an account ends the authority of its service as well
+    let acc_tx = tx_builder
+        .create_delegating_account_signed(
+            "acc_3",
+            "service_1",
+            SigningKey::new_with_algorithm(algorithm).unwrap(),
+        )
+        .commit();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    let recovery_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let recovery = RecoveryConfig::new(vec![recovery_key.verifying_key()], 2);
+    let set_recovery_tx =
+        tx_builder.set_recovery_verified_with_root("acc_3", Some(recovery)).commit();
+    tree.process_transaction(set_recovery_tx, 0).unwrap();
+
+    let recovered_key = SigningKey::new_with_algorithm(algorithm).unwrap();
+    let initiate_tx = tx_builder
+        .initiate_recovery("acc_3", recovered_key.verifying_key(), &recovery_key)
+        .commit();
+    tree.process_transaction(initiate_tx, 0).unwrap();
+
+    tx_builder.set_epoch(2);
+    let complete_tx = tx_builder.complete_recovery("acc_3", &recovery_key).commit();
+    tree.process_transaction(complete_tx, 2).unwrap();
+
+    let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_3")).unwrap() else {
+        panic!("Expected account to be found");
+    };
+    assert_eq!(account.delegate(), None);
+
+    let after_recovery_tx = tx_builder
+        .delegated_revoke_key("acc_3", recovered_key.verifying_key(), &service_key)
+        .build();
+    assert!(tree.process_transaction(after_recovery_tx, 2).is_err());
+}
+
+fn test_batch_signature_verification(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    let data_tx = tx_builder
+        .add_randomly_signed_data_verified_with_root(algorithm, "acc_1", b"data".to_vec())
+        .commit();
+
+    let mut batch = tree.process_batch(vec![service_tx, acc_tx, data_tx], 0).unwrap();
+    assert_eq!(batch.proofs.len(), 3);
+    assert!(batch.verify().is_ok());
+
+    // Signatures are verified at the end of the batch, which still fails if
+    // any of them is invalid
+    let Proof::Update(update_proof) = &mut batch.proofs[2] else {
+        panic!("Expected update proof");
+    };
+    let payload = update_proof.tx.get_signature_payload(&batch.params.chain_id).unwrap();
+    update_proof.tx.signature = SigningKey::new_with_algorithm(algorithm).unwrap().sign(&payload);
+    assert!(batch.verify().is_err());
+}
+
+fn test_get_at_version(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    let old_version = tree.version();
+    let old_commitment = tree.get_commitment().unwrap();
+    let old_account = tx_builder.get_account("acc_1").unwrap().clone();
+
+    let key_tx = tx_builder.add_random_key_verified_with_root(algorithm, "acc_1").commit();
+    tree.process_transaction(key_tx, 1).unwrap();
+    let acc_2_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_2", "service_1").commit();
+    tree.process_transaction(acc_2_tx, 1).unwrap();
+
+    let key_hash = KeyHash::with::<TreeHasher>("acc_1");
+    let Found(account, proof) = tree.get_at(key_hash, old_version).unwrap() else {
+        panic!("Historical account not found")
+    };
+    assert_eq!(*account, old_account);
+    assert_eq!(proof.root, old_commitment);
+    assert!(proof.verify_existence(&account).is_ok());
+
+    // Accounts created later are not part of the old state
+    let NotFound(proof) = tree.get_at(KeyHash::with::<TreeHasher>("acc_2"), old_version).unwrap()
+    else {
+        panic!("Account created later should not exist at the old version")
+    };
+    assert_eq!(proof.root, old_commitment);
+    assert!(proof.verify_nonexistence().is_ok());
+
+    let current = tree.get(key_hash).unwrap();
+    assert!(matches!(current, Found(acc, _) if *acc == *tx_builder.get_account("acc_1").unwrap()));
+
+    assert!(tree.get_at(key_hash, tree.version() + 1).is_err());
+}
+
+fn test_encryption_keys(algorithm: CryptoAlgorithm) {
+    let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
+    let mut tx_builder = TransactionBuilder::new();
+
+    let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
+    let acc_tx =
+        tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc_tx, 0).unwrap();
+
+    let x25519_key = DecryptionKey::new_x25519().encryption_key();
+    let secp256r1_key = DecryptionKey::new_secp256r1().encryption_key();
+
+    for key in [x25519_key.clone(), secp256r1_key.clone()] {
+        let add_tx = tx_builder.add_encryption_key_verified_with_root("acc_1", key).commit();
+        let Proof::Update(update_proof) = tree.process_transaction(add_tx, 0).unwrap() else {
+            panic!("Processing encryption key addition failed")
+        };
+        assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
+    }
+
+    // Adding the same key twice fails
+    let duplicate_tx =
+        tx_builder.add_encryption_key_verified_with_root("acc_1", x25519_key.clone()).build();
+    assert!(tree.process_transaction(duplicate_tx, 0).is_err());
+
+    // Encryption keys are not accepted on networks that don't allow them
+    let params = ProtocolParams {
+        allowed_algorithms: vec![algorithm],
+        ..Default::default()
+    };
+    let other_key = DecryptionKey::new_x25519().encryption_key();
+    let disallowed_op = Operation::AddEncryptionKey { key: other_key };
+    assert!(disallowed_op.validate_basic(&params).is_err());
+
+    let revoke_tx =
+        tx_builder.revoke_encryption_key_verified_with_root("acc_1", x25519_key.clone()).commit();
+    tree.process_transaction(revoke_tx, 0).unwrap();
+
+    let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap() else {
+        panic!("Expected account to be found");
+    };
+    assert_eq!(account.encryption_keys(), &[secp256r1_key]);
+    assert_eq!(account.valid_keys().len(), 1);
+}
+
 fn test_data_ops(algorithm: CryptoAlgorithm) {
     let mut tree = KeyDirectoryTree::new(Arc::new(MockTreeStore::default()));
     let mut tx_builder = TransactionBuilder::new();
 
     let service_tx = tx_builder.register_service_with_random_keys(algorithm, "service_1").commit();
-    tree.process_transaction(service_tx).unwrap();
+    tree.process_transaction(service_tx, 0).unwrap();
 
     let acc1_tx =
         tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
-    tree.process_transaction(acc1_tx).unwrap();
+    tree.process_transaction(acc1_tx, 0).unwrap();
 
     let add_data_1_tx = tx_builder
         .add_internally_signed_data_verified_with_root("acc_1", b"test data 1".to_vec())
         .commit();
-    let Proof::Update(update_proof) = tree.process_transaction(add_data_1_tx).unwrap() else {
+    let Proof::Update(update_proof) = tree.process_transaction(add_data_1_tx, 0).unwrap() else {
         panic!("Processing data update failed");
     };
-    assert!(update_proof.verify().is_ok());
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
 
     let add_data_2_tx = tx_builder
         .add_randomly_signed_data_verified_with_root(algorithm, "acc_1", b"test data 2".to_vec())
         .commit();
-    let Proof::Update(update_proof) = tree.process_transaction(add_data_2_tx).unwrap() else {
+    let Proof::Update(update_proof) = tree.process_transaction(add_data_2_tx, 0).unwrap() else {
         panic!("Processing signed data update failed");
     };
-    assert!(update_proof.verify().is_ok());
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
 
     // Verify account data after updates
     let Found(account, membership_proof) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap()
@@ -211,10 +1381,10 @@ fn test_data_ops(algorithm: CryptoAlgorithm) {
             b"replacement data".to_vec(),
         )
         .commit();
-    let Proof::Update(update_proof) = tree.process_transaction(set_data_1_tx).unwrap() else {
+    let Proof::Update(update_proof) = tree.process_transaction(set_data_1_tx, 0).unwrap() else {
         panic!("Processing signed data update failed");
     };
-    assert!(update_proof.verify().is_ok());
+    assert!(update_proof.verify(None, &ValidationContext::default()).is_ok());
 
     let Found(account, _) = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap() else {
         panic!("Expected account to be found after data updates");
@@ -241,7 +1411,7 @@ fn test_data_ops(algorithm: CryptoAlgorithm) {
             invalid_signature_bundle,
         )
         .build();
-    assert!(tree.process_transaction(invalid_data_tx).is_err());
+    assert!(tree.process_transaction(invalid_data_tx, 0).is_err());
 }
 
 fn test_multiple_inserts_and_updates(algorithm: CryptoAlgorithm) {
@@ -254,24 +1424,24 @@ fn test_multiple_inserts_and_updates(algorithm: CryptoAlgorithm) {
     let acc2_tx =
         tx_builder.create_account_with_random_key_signed(algorithm, "acc_2", "service_1").commit();
 
-    tree.process_transaction(service_tx).unwrap();
+    tree.process_transaction(service_tx, 0).unwrap();
 
-    tree.process_transaction(acc1_tx).unwrap();
-    tree.process_transaction(acc2_tx).unwrap();
+    tree.process_transaction(acc1_tx, 0).unwrap();
+    tree.process_transaction(acc2_tx, 0).unwrap();
 
     // Do insert and update accounts using the correct key indices
     let key_1_tx = tx_builder.add_random_key_verified_with_root(algorithm, "acc_1").commit();
-    tree.process_transaction(key_1_tx).unwrap();
+    tree.process_transaction(key_1_tx, 0).unwrap();
 
     let data_1_tx = tx_builder
         .add_internally_signed_data_verified_with_root("acc_2", b"unsigned".to_vec())
         .commit();
-    tree.process_transaction(data_1_tx).unwrap();
+    tree.process_transaction(data_1_tx, 0).unwrap();
 
     let data_2_tx = tx_builder
         .add_randomly_signed_data_verified_with_root(algorithm, "acc_2", b"signed".to_vec())
         .commit();
-    tree.process_transaction(data_2_tx).unwrap();
+    tree.process_transaction(data_2_tx, 0).unwrap();
 
     let get_result1 = tree.get(KeyHash::with::<TreeHasher>("acc_1")).unwrap();
     let get_result2 = tree.get(KeyHash::with::<TreeHasher>("acc_2")).unwrap();
@@ -293,16 +1463,16 @@ fn test_interleaved_inserts_and_updates(algorithm: CryptoAlgorithm) {
     let acc2_tx =
         tx_builder.create_account_with_random_key_signed(algorithm, "acc_2", "service_1").commit();
 
-    tree.process_transaction(service_tx).unwrap();
-    tree.process_transaction(acc1_tx).unwrap();
+    tree.process_transaction(service_tx, 0).unwrap();
+    tree.process_transaction(acc1_tx, 0).unwrap();
 
     let add_key_to_1_tx = tx_builder.add_random_key_verified_with_root(algorithm, "acc_1").commit();
-    tree.process_transaction(add_key_to_1_tx).unwrap();
+    tree.process_transaction(add_key_to_1_tx, 0).unwrap();
 
-    tree.process_transaction(acc2_tx).unwrap();
+    tree.process_transaction(acc2_tx, 0).unwrap();
 
     let add_key_to_2_tx = tx_builder.add_random_key_verified_with_root(algorithm, "acc_2").commit();
-    let last_proof = tree.process_transaction(add_key_to_2_tx).unwrap();
+    let last_proof = tree.process_transaction(add_key_to_2_tx, 0).unwrap();
 
     // Update account_2 using the correct key index
     let Proof::Update(update_proof) = last_proof else {
@@ -328,10 +1498,10 @@ fn test_root_hash_changes(algorithm: CryptoAlgorithm) {
     let account1_tx =
         tx_builder.create_account_with_random_key_signed(algorithm, "acc_1", "service_1").commit();
 
-    tree.process_transaction(service_tx).unwrap();
+    tree.process_transaction(service_tx, 0).unwrap();
 
     let root_before = tree.get_current_root().unwrap();
-    tree.process_transaction(account1_tx).unwrap();
+    tree.process_transaction(account1_tx, 0).unwrap();
     let root_after = tree.get_current_root().unwrap();
 
     assert_ne!(root_before, root_after);
@@ -347,10 +1517,10 @@ fn test_batch_writing(algorithm: CryptoAlgorithm) {
     let account2_tx =
         tx_builder.create_account_with_random_key_signed(algorithm, "acc_2", "service_1").commit();
 
-    tree.process_transaction(service_tx).unwrap();
+    tree.process_transaction(service_tx, 0).unwrap();
 
     println!("Inserting acc_1");
-    tree.process_transaction(account1_tx).unwrap();
+    tree.process_transaction(account1_tx, 0).unwrap();
 
     println!("Tree state after first insert: {:?}", tree.get_commitment());
 
@@ -359,7 +1529,7 @@ fn test_batch_writing(algorithm: CryptoAlgorithm) {
     println!("Get result for key1 after first write: {:?}", get_result1);
 
     println!("Inserting acc_2");
-    tree.process_transaction(account2_tx).unwrap();
+    tree.process_transaction(account2_tx, 0).unwrap();
 
     println!("Tree state after 2nd insert: {:?}", tree.get_commitment());
 
@@ -394,6 +1564,21 @@ macro_rules! generate_algorithm_tests {
             fn [<$test_fn _secp256r1>]() {
                 $test_fn(CryptoAlgorithm::Secp256r1);
             }
+
+            #[test]
+            fn [<$test_fn _ethereum>]() {
+                $test_fn(CryptoAlgorithm::Ethereum);
+            }
+
+            #[test]
+            fn [<$test_fn _bls12381>]() {
+                $test_fn(CryptoAlgorithm::Bls12381);
+            }
+
+            #[test]
+            fn [<$test_fn _ml_dsa_65>]() {
+                $test_fn(CryptoAlgorithm::MlDsa65);
+            }
         }
     };
 }
@@ -401,10 +1586,27 @@ macro_rules! generate_algorithm_tests {
 generate_algorithm_tests!(test_insert_and_get);
 generate_algorithm_tests!(test_insert_for_nonexistent_service_fails);
 generate_algorithm_tests!(test_insert_with_invalid_service_challenge_fails);
+generate_algorithm_tests!(test_account_creation_cannot_be_front_run);
 generate_algorithm_tests!(test_insert_duplicate_key);
 generate_algorithm_tests!(test_update_existing_key);
 generate_algorithm_tests!(test_update_non_existing_key);
+generate_algorithm_tests!(test_expired_key_cannot_sign);
+generate_algorithm_tests!(test_threshold_policy);
+generate_algorithm_tests!(test_key_roles);
+generate_algorithm_tests!(test_encryption_keys);
+generate_algorithm_tests!(test_timelocked_recovery);
+generate_algorithm_tests!(test_service_challenge_rotation);
+generate_algorithm_tests!(test_service_creation_gates);
+generate_algorithm_tests!(test_deactivate_account);
+generate_algorithm_tests!(test_multi_operation_transaction);
 generate_algorithm_tests!(test_data_ops);
+generate_algorithm_tests!(test_remove_data);
+generate_algorithm_tests!(test_protocol_params_limits);
+generate_algorithm_tests!(test_chain_id_replay_protection);
+generate_algorithm_tests!(test_transaction_expiry);
+generate_algorithm_tests!(test_service_delegation);
+generate_algorithm_tests!(test_batch_signature_verification);
+generate_algorithm_tests!(test_get_at_version);
 generate_algorithm_tests!(test_multiple_inserts_and_updates);
 generate_algorithm_tests!(test_interleaved_inserts_and_updates);
 generate_algorithm_tests!(test_root_hash_changes);
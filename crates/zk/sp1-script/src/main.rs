@@ -151,7 +151,7 @@ fn create_preparation_batch(
         transactions.push(transaction);
     }
 
-    tree.process_batch(transactions).unwrap()
+    tree.process_batch(transactions, 0).unwrap()
 }
 
 /// Create a batch of transactions to benchmark the performance of the tree
@@ -228,7 +228,7 @@ fn create_benchmark_batch(
         transactions.push(transaction);
     }
 
-    tree.process_batch(transactions).unwrap()
+    tree.process_batch(transactions, 0).unwrap()
 }
 
 #[tokio::main]
@@ -13,4 +13,6 @@ pub fn main() {
     batch.verify().unwrap();
     println!("cycle-tracker-end: proof-iteration");
     sp1_zkvm::io::commit_slice(&batch.new_root.0);
+    sp1_zkvm::io::commit_slice(&batch.epoch.to_le_bytes());
+    sp1_zkvm::io::commit_slice(&batch.params.digest().unwrap().0);
 }